@@ -20,6 +20,10 @@ struct MatchState {
     state_hash: Vec<u8>,
     players: Vec<String>,
     seed: u64,
+    // Simulation state, pinned to the ruleset requested at creation
+    state: simcore::State,
+    // Incrementally maintained state hash (SubmitAction budget is 25ms)
+    hasher: simcore::IncrementalHasher,
     // Idempotency: track processed action_ids
     processed_actions: HashMap<String, Acknowledgement>,
 }
@@ -61,6 +65,125 @@ impl MatchService {
     }
 }
 
+impl MatchState {
+    /// Simcore id of `player_id`; players are numbered in roster order
+    fn player(&self, player_id: &str) -> Option<simcore::PlayerId> {
+        self.players
+            .iter()
+            .position(|p| p == player_id)
+            .map(|i| simcore::PlayerId(i as u64))
+    }
+
+    /// Map view for `player_id`; empty if they are not on the roster
+    ///
+    /// Terrain is known everywhere, so every tile is listed along with whether the player
+    /// can see it and whether it lies inside their supply network. Units are the ones the
+    /// player owns or can currently see.
+    fn view(&self, player_id: &str) -> Result<View, simcore::SimError> {
+        let Some(player) = self.player(player_id) else {
+            return Ok(View::default());
+        };
+        let visible = simcore::fog::visible_tiles(&self.state, player)?;
+        let supplied = simcore::supply::supply_overlay(&self.state, player)?;
+        let combat = &self.state.rules()?.combat;
+        let units = simcore::fog::known_units(&self.state, player, &visible)
+            .map(|u| UnitInfo {
+                unit_id: u.id.0.to_string(),
+                kind: u.kind.clone(),
+                x: u.pos.x,
+                y: u.pos.y,
+                owner: self
+                    .players
+                    .get(u.owner.0 as usize)
+                    .cloned()
+                    .unwrap_or_default(),
+                hp: u.hp,
+                moves_left: u.moves_left,
+                fortified: u.fortified,
+                fortify_pct: u.fortify_pct(combat),
+            })
+            .collect();
+        let map = &self.state.map;
+        let tiles = map
+            .coords()
+            .filter_map(|c| {
+                let tile = map.tile(c)?;
+                Some(TileInfo {
+                    x: c.x,
+                    y: c.y,
+                    terrain: format!("{:?}", tile.terrain).to_lowercase(),
+                    features: tile
+                        .resource
+                        .iter()
+                        .map(|r| format!("{:?}", r).to_lowercase())
+                        .collect(),
+                    visible: visible.contains(&c),
+                    supplied: supplied.contains(&c),
+                })
+            })
+            .collect();
+        Ok(View {
+            tiles,
+            cities: vec![],
+            units,
+        })
+    }
+
+    /// Actions `player_id` may take now, encoded as `submit_action` reads them; empty if
+    /// they are not on the roster
+    fn legal_actions(&self, player_id: &str) -> Result<Vec<ActionLite>, serde_json::Error> {
+        let Some(player) = self.player(player_id) else {
+            return Ok(vec![]);
+        };
+        simcore::enumerate_legal_actions(&self.state, player)
+            .iter()
+            .map(|action| {
+                Ok(ActionLite {
+                    action_type: action.kind().to_string(),
+                    payload: serde_json::to_vec(action)?,
+                })
+            })
+            .collect()
+    }
+
+    /// Research view for `player_id`; empty if they are not on the roster
+    fn tech_state(&self, player_id: &str) -> TechState {
+        let player = self.player(player_id).and_then(|id| self.state.player(id));
+        let (Some(player), Ok(rules)) = (player, self.state.rules()) else {
+            return TechState::default();
+        };
+        // Frozen techs are reported only under `frozen`, known or not
+        TechState {
+            known: player
+                .known_techs
+                .iter()
+                .filter(|t| simcore::tech::has(player, t))
+                .cloned()
+                .collect(),
+            available: simcore::tech::available(rules, player),
+            frozen: player.frozen_techs.keys().cloned().collect(),
+        }
+    }
+
+    /// This turn's yields for `player_id`, rounded down; zeros for unknown players
+    fn yields(&self, player_id: &str) -> Yields {
+        let Some(player) = self.player(player_id) else {
+            return Yields::default();
+        };
+        let Ok(y) = simcore::yields::player_yields(&self.state, player) else {
+            return Yields::default();
+        };
+        Yields {
+            food: y.food.floor(),
+            production: y.production.floor(),
+            gold: y.gold.floor(),
+            science: y.science.floor(),
+            culture: y.culture.floor(),
+            influence: y.influence.floor(),
+        }
+    }
+}
+
 impl Default for MatchService {
     fn default() -> Self {
         Self::new()
@@ -78,10 +201,21 @@ impl Match for MatchService {
         // Generate match_id
         let match_id = format!("match_{}", req.seed);
 
-        // Mock initial state
-        let initial_state = simcore::State::new();
-        let state_hash = simcore::state_hash(&initial_state);
-        let hash_bytes = state_hash.0.to_le_bytes().to_vec();
+        // Empty rules_version selects the default ruleset
+        let rules_version = if req.rules_version.is_empty() {
+            simcore::rules::DEFAULT_VERSION
+        } else {
+            req.rules_version.as_str()
+        };
+        let mut initial_state = simcore::State::with_rules(rules_version)
+            .map_err(|e| Status::invalid_argument(e.to_string()))?;
+        initial_state.rng = simcore::Rng::new(req.seed);
+        // Players are numbered in the order they were configured
+        for (i, p) in req.players.iter().enumerate() {
+            initial_state.add_player(simcore::PlayerId(i as u64), &p.player_id);
+        }
+        let hasher = simcore::IncrementalHasher::new(&initial_state);
+        let hash_bytes = hasher.hash().0.to_le_bytes().to_vec();
 
         // Store match state
         let match_state = MatchState {
@@ -90,6 +224,8 @@ impl Match for MatchService {
             state_hash: hash_bytes.clone(),
             players: req.players.iter().map(|p| p.player_id.clone()).collect(),
             seed: req.seed,
+            state: initial_state,
+            hasher,
             processed_actions: HashMap::new(),
         };
 
@@ -117,46 +253,22 @@ impl Match for MatchService {
             .get(&req.match_id)
             .ok_or_else(|| Status::not_found("Match not found"))?;
 
-        // Mock observation from current state
+        let view = match_state
+            .view(&req.player_id)
+            .map_err(|e| Status::internal(e.to_string()))?;
         let observation = Observation {
             turn: match_state.turn,
             player_id: req.player_id.clone(),
-            view: Some(View {
-                tiles: vec![
-                    TileInfo {
-                        x: 0,
-                        y: 0,
-                        terrain: "plains".to_string(),
-                        features: vec![],
-                        visible: true,
-                    },
-                ],
-                cities: vec![],
-                units: vec![],
-            }),
-            yields: Some(Yields {
-                food: 2,
-                production: 1,
-                gold: 3,
-                science: 1,
-                culture: 1,
-                influence: 0,
-            }),
-            tech: Some(TechState {
-                known: vec!["agriculture".to_string()],
-                available: vec!["mining".to_string(), "pottery".to_string()],
-                frozen: vec![],
-            }),
+            view: Some(view),
+            yields: Some(match_state.yields(&req.player_id)),
+            tech: Some(match_state.tech_state(&req.player_id)),
             diplomacy: Some(DiplomacyState {
                 relations: vec![],
                 open_offers: vec![],
             }),
-            legal_actions: vec![
-                ActionLite {
-                    action_type: "EndTurn".to_string(),
-                    payload: b"{}".to_vec(),
-                },
-            ],
+            legal_actions: match_state
+                .legal_actions(&req.player_id)
+                .map_err(|e| Status::internal(e.to_string()))?,
             state_hash: match_state.state_hash.clone(),
         };
 
@@ -194,27 +306,60 @@ impl Match for MatchService {
             return Ok(Response::new(ack.clone()));
         }
 
-        // Deserialize and validate action (stub: always accept)
-        let action: Result<simcore::Action, _> = serde_json::from_slice(&req.action_bytes);
-        if action.is_err() {
+        // Deserialize action
+        let action: simcore::Action = match serde_json::from_slice(&req.action_bytes) {
+            Ok(action) => action,
+            Err(_) => {
+                return Ok(Response::new(Acknowledgement {
+                    accepted: false,
+                    error: "Invalid action JSON".to_string(),
+                    action_id: req.action_id,
+                    new_state_hash: vec![],
+                    error_code: 0,
+                }));
+            }
+        };
+
+        // Validate on behalf of the submitting player
+        let Some(player) = match_state.player(&req.player_id) else {
+            // Roster ids are dense, so the first id past the end is never registered
+            let unknown = simcore::PlayerId(match_state.players.len() as u64);
+            let err = simcore::ValidationError::PlayerNotFound(unknown);
             return Ok(Response::new(Acknowledgement {
                 accepted: false,
-                error: "Invalid action JSON".to_string(),
+                error: format!("player {} not found", req.player_id),
                 action_id: req.action_id,
-                new_state_hash: vec![],
+                new_state_hash: match_state.state_hash.clone(),
+                error_code: err.code(),
             }));
-        }
-
-        // Mock state update
-        let new_hash = (match_state.state_hash[0] as u128 + 1).to_le_bytes().to_vec();
-        match_state.state_hash = new_hash.clone();
-
-        // Create acknowledgement
-        let ack = Acknowledgement {
-            accepted: true,
-            error: String::new(),
-            action_id: req.action_id.clone(),
-            new_state_hash: new_hash,
+        };
+        let result = simcore::apply_action(&mut match_state.state, player, action);
+
+        // Fold the touched entities into the running hash
+        let ack = match result {
+            Ok(effects) => {
+                let hash = match_state
+                    .hasher
+                    .update(&match_state.state, &effects)
+                    .map_err(|e| Status::internal(e.to_string()))?;
+                let new_hash = hash.0.to_le_bytes().to_vec();
+                match_state.state_hash = new_hash.clone();
+                match_state.turn = match_state.state.turn;
+                Acknowledgement {
+                    accepted: true,
+                    error: String::new(),
+                    action_id: req.action_id.clone(),
+                    new_state_hash: new_hash,
+                    error_code: 0,
+                }
+            }
+            Err(e) => Acknowledgement {
+                accepted: false,
+                error: e.to_string(),
+                action_id: req.action_id.clone(),
+                new_state_hash: match_state.state_hash.clone(),
+                error_code: e.code().unwrap_or(0),
+            },
         };
 
         // Store for idempotency
@@ -234,17 +379,25 @@ impl Match for MatchService {
             .get_mut(&req.match_id)
             .ok_or_else(|| Status::not_found("Match not found"))?;
 
-        // Advance turn
-        match_state.turn += 1;
-
-        // Mock events
-        let events = vec![
-            GameEvent {
-                event_type: "TurnAdvanced".to_string(),
-                description: format!("Turn {} begins", match_state.turn),
-                payload: vec![],
-            },
-        ];
+        // Advance turn through the inter-turn pipeline and fold its deltas into the hash
+        let effects = simcore::end_turn_with_effects(&mut match_state.state)
+            .map_err(|e| Status::internal(e.to_string()))?;
+        let hash = match_state
+            .hasher
+            .update(&match_state.state, &effects)
+            .map_err(|e| Status::internal(e.to_string()))?;
+        match_state.state_hash = hash.0.to_le_bytes().to_vec();
+        match_state.turn = match_state.state.turn;
+
+        let events = effects
+            .events
+            .iter()
+            .map(|event| GameEvent {
+                event_type: event.kind().to_string(),
+                description: format!("{:?}", event),
+                payload: serde_json::to_vec(event).unwrap_or_default(),
+            })
+            .collect();
 
         Ok(Response::new(EventBatch {
             turn: match_state.turn,
@@ -273,3 +426,139 @@ pub fn create_server() -> MatchServer<MatchService> {
     MatchServer::new(MatchService::new())
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use simcore::{Map, PlayerId, TileCoord};
+
+    async fn create(service: &MatchService, players: &[&str]) -> String {
+        let request = CreateMatchRequest {
+            players: players
+                .iter()
+                .map(|p| PlayerConfig {
+                    player_id: p.to_string(),
+                    ..Default::default()
+                })
+                .collect(),
+            seed: 7,
+            ..Default::default()
+        };
+        let response = service.create_match(Request::new(request)).await.unwrap();
+        response.into_inner().match_id
+    }
+
+    /// Edit a match's simulation state directly, bypassing actions
+    fn edit(service: &MatchService, match_id: &str, f: impl FnOnce(&mut simcore::State)) {
+        let mut matches = service.matches.write().unwrap();
+        f(&mut matches.get_mut(match_id).unwrap().state);
+    }
+
+    async fn view(service: &MatchService, match_id: &str, player_id: &str) -> View {
+        let request = ObservationRequest {
+            match_id: match_id.to_string(),
+            player_id: player_id.to_string(),
+        };
+        let observation = service
+            .get_observation(Request::new(request))
+            .await
+            .unwrap();
+        observation.into_inner().view.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_view_marks_supplied_tiles() {
+        let service = MatchService::new();
+        let id = create(&service, &["ana", "bo"]).await;
+        edit(&service, &id, |state| {
+            state.map = Map::new(12, 12).unwrap();
+            state
+                .found_city(PlayerId(0), "Jericho", TileCoord::new(3, 3))
+                .unwrap();
+        });
+
+        let ours = view(&service, &id, "ana").await;
+        assert_eq!(ours.tiles.len(), 144);
+        let tile = |x, y| ours.tiles.iter().find(|t| (t.x, t.y) == (x, y)).unwrap();
+        assert!(tile(3, 3).supplied && tile(3, 3).visible);
+        assert!(!tile(11, 11).supplied && !tile(11, 11).visible);
+
+        let theirs = view(&service, &id, "bo").await;
+        assert!(theirs.tiles.iter().all(|t| !t.supplied));
+        assert!(view(&service, &id, "nobody").await.tiles.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_view_reports_fortification() {
+        let service = MatchService::new();
+        let id = create(&service, &["ana", "bo"]).await;
+        let mut expected_pct = 0;
+        edit(&service, &id, |state| {
+            state.map = Map::new(12, 12).unwrap();
+            let ours = state
+                .spawn_unit(PlayerId(0), "warrior", TileCoord::new(2, 2))
+                .unwrap();
+            state
+                .spawn_unit(PlayerId(1), "warrior", TileCoord::new(3, 2))
+                .unwrap();
+            state
+                .spawn_unit(PlayerId(1), "warrior", TileCoord::new(10, 10))
+                .unwrap();
+            let combat = state.rules().unwrap().combat.clone();
+            let unit = state.units.get_mut(&ours).unwrap();
+            unit.fortified = true;
+            unit.fortify_turns = 1;
+            expected_pct = unit.fortify_pct(&combat);
+        });
+
+        let units = view(&service, &id, "ana").await.units;
+        assert_eq!(units.len(), 2, "the far enemy warrior is hidden");
+        let ours = units.iter().find(|u| u.owner == "ana").unwrap();
+        assert!(ours.fortified);
+        assert!(expected_pct > 0);
+        assert_eq!(ours.fortify_pct, expected_pct);
+        let theirs = units.iter().find(|u| u.owner == "bo").unwrap();
+        assert_eq!((theirs.x, theirs.y), (3, 2));
+        assert!(!theirs.fortified);
+        assert_eq!(theirs.fortify_pct, 0);
+    }
+
+    #[tokio::test]
+    async fn test_legal_actions_round_trip_through_submit() {
+        let service = MatchService::new();
+        let id = create(&service, &["ana", "bo"]).await;
+        edit(&service, &id, |state| {
+            state.map = Map::new(12, 12).unwrap();
+            state
+                .spawn_unit(PlayerId(0), "warrior", TileCoord::new(2, 2))
+                .unwrap();
+        });
+        let request = ObservationRequest {
+            match_id: id.clone(),
+            player_id: "ana".to_string(),
+        };
+        let observation = service
+            .get_observation(Request::new(request))
+            .await
+            .unwrap()
+            .into_inner();
+        let legal = observation.legal_actions;
+        assert!(legal.iter().any(|a| a.action_type == "EndTurn"));
+        let fortify = legal
+            .iter()
+            .find(|a| a.action_type == "Fortify")
+            .expect("the warrior can fortify");
+
+        let action_id =
+            MatchService::compute_action_id(&id, observation.turn, "ana", &fortify.payload, &[]);
+        let request = ActionRequest {
+            match_id: id.clone(),
+            player_id: "ana".to_string(),
+            turn: observation.turn,
+            action_id,
+            action_bytes: fortify.payload.clone(),
+            ..Default::default()
+        };
+        let ack = service.submit_action(Request::new(request)).await.unwrap();
+        assert!(ack.into_inner().accepted);
+    }
+}
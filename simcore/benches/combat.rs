@@ -0,0 +1,45 @@
+//! Combat microbench; the contract budget is 0.2ms per exchange on standard units
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use simcore::combat::{resolve_melee, resolve_ranged, CombatContext};
+use simcore::rules::DEFAULT_VERSION;
+use simcore::{PlayerId, Rules, TileCoord, Unit, UnitClass, UnitId};
+
+fn unit(id: u64, owner: u64, kind: &str) -> Unit {
+    Unit::new(
+        UnitId(id),
+        PlayerId(owner),
+        kind,
+        UnitClass::Combat,
+        TileCoord::new(0, 0),
+        2,
+    )
+}
+
+fn bench_combat(c: &mut Criterion) {
+    let rules = Rules::load(DEFAULT_VERSION).unwrap();
+    let warrior = unit(1, 0, "warrior");
+    let archer = unit(2, 0, "archer");
+    let mut defender = unit(3, 1, "spearman");
+    defender.fortify_turns = 2;
+    let ctx = CombatContext {
+        flankers: 2,
+        across_river: true,
+        high_ground: true,
+        terrain_def_pct: 25,
+        in_city: false,
+        counter_fire: false,
+        attacker_policy_pct: 5,
+        defender_policy_pct: 0,
+    };
+
+    c.bench_function("resolve_melee", |b| {
+        b.iter(|| resolve_melee(black_box(&warrior), black_box(&defender), &ctx, rules))
+    });
+    c.bench_function("resolve_ranged", |b| {
+        b.iter(|| resolve_ranged(black_box(&archer), black_box(&defender), &ctx, rules))
+    });
+}
+
+criterion_group!(benches, bench_combat);
+criterion_main!(benches);
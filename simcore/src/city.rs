@@ -0,0 +1,244 @@
+//! Cities, production queues and districts
+
+use crate::effects::{Event, Recorder};
+use crate::rules::CombatConstants;
+use crate::{
+    district, tech, validation, CityId, Fixed, PlayerId, SimError, State, TileCoord,
+    ValidationError,
+};
+use serde::{Deserialize, Serialize};
+
+/// Longest production queue a city may hold
+pub const MAX_QUEUE_LEN: usize = 5;
+
+/// What a production order will yield when finished
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ProductionItem {
+    Unit { kind: String },
+    District { kind: String, tile: TileCoord },
+}
+
+/// Queued production order; the cost is fixed when the order is placed
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProductionOrder {
+    pub item: ProductionItem,
+    pub cost: i32,
+}
+
+/// A district placed on a tile owned by a city
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct District {
+    pub kind: String,
+    pub tile: TileCoord,
+}
+
+/// A city
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct City {
+    pub id: CityId,
+    pub owner: PlayerId,
+    pub name: String,
+    pub pos: TileCoord,
+    pub population: i32,
+    pub food_stored: Fixed,
+    pub production_stored: Fixed,
+    pub queue: Vec<ProductionOrder>,
+    pub districts: Vec<District>,
+    /// Wall HP lost; the walls are breached once this reaches `city_wall_hp`
+    pub wall_damage: i32,
+    /// Fractional wall damage banked until it reaches a whole point
+    pub wall_wounds: Fixed,
+    /// The city has used its ranged strike this turn
+    pub struck: bool,
+}
+
+impl City {
+    pub fn new(id: CityId, owner: PlayerId, name: impl Into<String>, pos: TileCoord) -> Self {
+        Self {
+            id,
+            owner,
+            name: name.into(),
+            pos,
+            population: 1,
+            food_stored: Fixed::ZERO,
+            production_stored: Fixed::ZERO,
+            queue: Vec::new(),
+            districts: Vec::new(),
+            wall_damage: 0,
+            wall_wounds: Fixed::ZERO,
+            struck: false,
+        }
+    }
+
+    /// Wall HP remaining
+    pub fn wall_hp(&self, c: &CombatConstants) -> i32 {
+        (c.city_wall_hp - self.wall_damage).max(0)
+    }
+
+    /// Walls are down: the city loses its defense bonus and strike, and can be captured
+    pub fn is_breached(&self, c: &CombatConstants) -> bool {
+        self.wall_hp(c) == 0
+    }
+
+    /// Defense and ranged strike strength
+    pub fn strength(&self, c: &CombatConstants) -> i32 {
+        c.city_strength_base + c.city_strength_per_pop * self.population.max(0)
+    }
+
+    /// Food needed to grow to the next population; also the food storage cap
+    pub fn food_cap(&self) -> Fixed {
+        Fixed::from_int(15 + 8 * (self.population - 1).max(0))
+    }
+
+    /// Most production a city can bank toward its queue
+    pub fn production_cap(&self) -> Fixed {
+        Fixed::from_int(100 + 25 * self.population)
+    }
+
+    /// District slots unlocked by population: one, plus one per three citizens
+    pub fn district_slots(&self) -> usize {
+        1 + (self.population.max(0) / 3) as usize
+    }
+
+    /// Districts placed plus districts waiting in the queue
+    pub fn districts_committed(&self) -> usize {
+        self.districts.len()
+            + self
+                .queue
+                .iter()
+                .filter(|o| matches!(o.item, ProductionItem::District { .. }))
+                .count()
+    }
+
+    /// A district is placed or queued on `tile`
+    pub fn claims_tile(&self, tile: TileCoord) -> bool {
+        self.districts.iter().any(|d| d.tile == tile)
+            || self
+                .queue
+                .iter()
+                .any(|o| matches!(&o.item, ProductionItem::District { tile: t, .. } if *t == tile))
+    }
+
+    /// Add food, clamped to the storage cap
+    pub fn store_food(&mut self, amount: Fixed) {
+        self.food_stored = (self.food_stored + amount).clamp(Fixed::ZERO, self.food_cap());
+    }
+
+    /// Add production, clamped to the storage cap
+    pub fn store_production(&mut self, amount: Fixed) {
+        self.production_stored =
+            (self.production_stored + amount).clamp(Fixed::ZERO, self.production_cap());
+    }
+}
+
+fn city_or_err(state: &State, id: CityId) -> Result<&City, ValidationError> {
+    state.city(id).ok_or(ValidationError::CityNotFound(id))
+}
+
+fn check_queue_space(city: &City) -> Result<(), ValidationError> {
+    if city.queue.len() >= MAX_QUEUE_LEN {
+        return Err(ValidationError::QueueFull(city.id));
+    }
+    Ok(())
+}
+
+pub(crate) fn validate_build_unit(
+    state: &State,
+    player: PlayerId,
+    id: CityId,
+    kind: &str,
+) -> Result<(), SimError> {
+    let Some(def) = state.rules()?.unit(kind) else {
+        return Err(ValidationError::UnknownUnitKind(kind.to_string()).into());
+    };
+    let city = city_or_err(state, id)?;
+    validation::check_owner(player, city.owner)?;
+    tech::check_unlocked(state, city.owner, def.requires_tech.as_deref())?;
+    Ok(check_queue_space(city)?)
+}
+
+pub(crate) fn validate_build_district(
+    state: &State,
+    player: PlayerId,
+    id: CityId,
+    kind: &str,
+    tile: TileCoord,
+) -> Result<(), SimError> {
+    let rules = state.rules()?;
+    let Some(def) = rules.district(kind) else {
+        return Err(ValidationError::UnknownDistrictKind(kind.to_string()).into());
+    };
+    let city = city_or_err(state, id)?;
+    validation::check_owner(player, city.owner)?;
+    tech::check_unlocked(state, city.owner, def.requires_tech.as_deref())?;
+    check_queue_space(city)?;
+    if city.districts_committed() >= city.district_slots() {
+        return Err(ValidationError::NoDistrictSlot(id).into());
+    }
+    Ok(district::check_placement(
+        state, rules, city, kind, def, tile,
+    )?)
+}
+
+/// Append an already validated order to a city's queue, fixing its cost from the rules
+pub(crate) fn enqueue(
+    state: &mut State,
+    id: CityId,
+    item: ProductionItem,
+    rec: &mut Recorder,
+) -> Result<(), SimError> {
+    let rules = state.rules()?;
+    let cost = match &item {
+        ProductionItem::Unit { kind } => rules.unit(kind).map(|d| d.cost),
+        ProductionItem::District { kind, .. } => rules.district(kind).map(|d| d.cost),
+    }
+    .ok_or_else(|| match &item {
+        ProductionItem::Unit { kind } => ValidationError::UnknownUnitKind(kind.clone()),
+        ProductionItem::District { kind, .. } => ValidationError::UnknownDistrictKind(kind.clone()),
+    })?;
+    if let Some(city) = state.cities.get_mut(&id) {
+        city.queue.push(ProductionOrder {
+            item: item.clone(),
+            cost,
+        });
+        rec.emit(Event::ProductionQueued { city: id, item });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn city() -> City {
+        City::new(CityId(0), PlayerId(0), "Abu Hureyra", TileCoord::new(2, 2))
+    }
+
+    #[test]
+    fn test_storage_is_capped() {
+        let mut city = city();
+        city.store_food(Fixed::from_int(1_000));
+        assert_eq!(city.food_stored, city.food_cap());
+        city.store_production(Fixed::from_int(1_000));
+        assert_eq!(city.production_stored, city.production_cap());
+        city.store_food(Fixed::from_int(-1_000));
+        assert_eq!(city.food_stored, Fixed::ZERO);
+    }
+
+    #[test]
+    fn test_slots_count_queued_districts() {
+        let mut city = city();
+        assert_eq!(city.district_slots(), 1);
+        city.queue.push(ProductionOrder {
+            item: ProductionItem::District {
+                kind: "campus".to_string(),
+                tile: TileCoord::new(3, 2),
+            },
+            cost: 60,
+        });
+        assert_eq!(city.districts_committed(), 1);
+        assert!(city.claims_tile(TileCoord::new(3, 2)));
+        city.population = 3;
+        assert_eq!(city.district_slots(), 2);
+    }
+}
@@ -0,0 +1,712 @@
+//! Deterministic combat (docs/contracts/combat.md)
+//!
+//! Each exchange is simultaneous and uses no RNG. Damage dealt is
+//! `K * (S_own / S_other)^alpha`, with effective strengths scaled by the contract's
+//! modifiers: flanking, river crossing and out-of-supply on the attacker; high ground,
+//! out-of-supply and capped terrain/city/fortify defense on the defender. Fractional
+//! damage accumulates in each unit's wound bank and only whole points come off HP.
+
+use crate::effects::{Event, Recorder};
+use crate::rules::{CombatConstants, Rules};
+use crate::{policy, validation, Fixed, PlayerId, SimError, State, Unit, UnitId, ValidationError};
+use serde::Serialize;
+
+/// Situational modifiers for one exchange
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct CombatContext {
+    /// Attacker's other combat units adjacent to the defender
+    pub flankers: u32,
+    /// Melee attack across a river
+    pub across_river: bool,
+    /// Defender stands higher than the attacker
+    pub high_ground: bool,
+    /// Terrain defense of the defender's tile, in percent
+    pub terrain_def_pct: i32,
+    /// Defender is in a city whose walls still stand
+    pub in_city: bool,
+    /// Ranged defender can return fire at the attacker's distance
+    pub counter_fire: bool,
+    /// Policy combat bonus of the attacking side, in percent
+    pub attacker_policy_pct: i32,
+    /// Policy combat bonus of the defending side, in percent
+    pub defender_policy_pct: i32,
+}
+
+/// Wound banks after an exchange
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Banks {
+    pub a: Fixed,
+    pub d: Fixed,
+}
+
+/// Outcome of one exchange: whole HP lost by each side, and their new wound banks
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Exchange {
+    pub to_def: i32,
+    pub to_att: i32,
+    pub banks: Banks,
+}
+
+impl CombatContext {
+    /// Read the modifiers for `attacker` striking `defender` off the map
+    pub fn between(state: &State, attacker: &Unit, defender: &Unit) -> Result<Self, SimError> {
+        let rules = state.rules()?;
+        let att_tile = state
+            .map
+            .tile(attacker.pos)
+            .ok_or(ValidationError::OutOfBounds(attacker.pos))?;
+        let def_tile = state
+            .map
+            .tile(defender.pos)
+            .ok_or(ValidationError::OutOfBounds(defender.pos))?;
+        let flankers = state
+            .map
+            .neighbors(defender.pos)
+            .flat_map(|n| state.units_at(n))
+            .filter(|u| {
+                u.id != attacker.id && u.owner == attacker.owner && u.is_combat() && u.is_alive()
+            })
+            .count() as u32;
+        let distance = attacker.pos.distance(defender.pos);
+        let counter_fire = rules
+            .unit(&defender.kind)
+            .is_some_and(|def| def.is_ranged() && distance <= def.range);
+        Ok(Self {
+            flankers,
+            across_river: state.map.river_between(attacker.pos, defender.pos),
+            high_ground: def_tile.elevation > att_tile.elevation,
+            terrain_def_pct: rules.terrain(def_tile.terrain).defense_pct,
+            in_city: state
+                .city_at(defender.pos)
+                .is_some_and(|city| !city.is_breached(&rules.combat)),
+            counter_fire,
+            attacker_policy_pct: policy::combat_pct(state, rules, attacker.owner),
+            defender_policy_pct: policy::combat_pct(state, rules, defender.owner),
+        })
+    }
+
+    /// Modifiers on the attacker's strike
+    pub fn attack_modifiers(
+        &self,
+        attacker: &Unit,
+        c: &CombatConstants,
+        melee: bool,
+    ) -> Vec<Modifier> {
+        let mut mods = Vec::new();
+        if self.flankers > 0 {
+            let pct = (c.flank_step_pct * self.flankers as i32).min(c.flank_cap_pct);
+            mods.push(Modifier::pct(ModifierKind::Flank(self.flankers), pct));
+        }
+        if melee && self.across_river {
+            mods.push(Modifier::pct(ModifierKind::River, c.river_penalty_pct));
+        }
+        push_nonzero(
+            &mut mods,
+            ModifierKind::OutOfSupply,
+            attacker.supply_penalty(c),
+        );
+        push_nonzero(
+            &mut mods,
+            ModifierKind::Policy,
+            Fixed::from_pct(self.attacker_policy_pct),
+        );
+        mods
+    }
+
+    /// Modifiers on the defender's strength; terrain, city and fortify share one cap,
+    /// reported as a negative `CoverCap` entry when it clamps
+    pub fn defense_modifiers(&self, defender: &Unit, c: &CombatConstants) -> Vec<Modifier> {
+        self.cover_modifiers(defender.fortify_bonus(c), defender.supply_penalty(c), c)
+    }
+
+    /// `defense_modifiers` for a defender with the given fortify bonus and supply penalty
+    pub(crate) fn cover_modifiers(
+        &self,
+        fortify: Fixed,
+        supply: Fixed,
+        c: &CombatConstants,
+    ) -> Vec<Modifier> {
+        let mut mods = Vec::new();
+        if self.high_ground {
+            mods.push(Modifier::pct(
+                ModifierKind::HighGround,
+                c.high_ground_def_pct,
+            ));
+        }
+        push_nonzero(
+            &mut mods,
+            ModifierKind::Terrain,
+            Fixed::from_pct(self.terrain_def_pct),
+        );
+        if self.in_city {
+            mods.push(Modifier::pct(ModifierKind::City, c.city_def_pct));
+        }
+        push_nonzero(&mut mods, ModifierKind::Fortify, fortify);
+        let cover =
+            Fixed::from_pct(self.terrain_def_pct + if self.in_city { c.city_def_pct } else { 0 })
+                + fortify;
+        let cap = Fixed::from_pct(c.terrain_cap_pct);
+        if cover > cap {
+            mods.push(Modifier {
+                kind: ModifierKind::CoverCap,
+                value: cap - cover,
+            });
+        }
+        push_nonzero(&mut mods, ModifierKind::OutOfSupply, supply);
+        push_nonzero(
+            &mut mods,
+            ModifierKind::Policy,
+            Fixed::from_pct(self.defender_policy_pct),
+        );
+        mods
+    }
+}
+
+/// Which rule adjusted a strength
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum ModifierKind {
+    /// Extra adjacent attackers
+    Flank(u32),
+    River,
+    HighGround,
+    Terrain,
+    City,
+    Fortify,
+    /// Clamp bringing terrain + city + fortify down to the cap
+    CoverCap,
+    OutOfSupply,
+    /// Slotted policy cards
+    Policy,
+}
+
+/// One strength adjustment, as a fraction (0.12 = +12%)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Modifier {
+    pub kind: ModifierKind,
+    pub value: Fixed,
+}
+
+impl Modifier {
+    fn pct(kind: ModifierKind, pct: i32) -> Self {
+        Self {
+            kind,
+            value: Fixed::from_pct(pct),
+        }
+    }
+}
+
+fn push_nonzero(mods: &mut Vec<Modifier>, kind: ModifierKind, value: Fixed) {
+    if value != Fixed::ZERO {
+        mods.push(Modifier { kind, value });
+    }
+}
+
+/// Strength multiplier from a modifier list, e.g. 1.12 for one flanker
+pub(crate) fn factor(mods: &[Modifier]) -> Fixed {
+    mods.iter().fold(Fixed::ONE, |acc, m| acc + m.value)
+}
+
+/// Raw damage `k * (own / other)^alpha`
+pub(crate) fn damage(k: i32, own: Fixed, other: Fixed, alpha: Fixed) -> Fixed {
+    if own <= Fixed::ZERO {
+        return Fixed::ZERO;
+    }
+    if other <= Fixed::ZERO {
+        return Fixed::from_int(crate::unit::MAX_HP);
+    }
+    let ratio = own / other;
+    Fixed::from_int(k) * ratio.pow(alpha).unwrap_or(Fixed::ZERO)
+}
+
+/// Add `raw` to `bank`, returning the whole points to apply and the new bank
+pub(crate) fn bank(bank: Fixed, raw: Fixed) -> (i32, Fixed) {
+    let total = bank + raw;
+    (total.floor(), total.frac())
+}
+
+/// One side's blow: `k` and the effective strengths of the striker and its target
+#[derive(Debug, Clone, Copy)]
+struct Strike {
+    k: i32,
+    own: Fixed,
+    other: Fixed,
+}
+
+/// Everything an exchange is computed from
+struct Setup {
+    att_mods: Vec<Modifier>,
+    def_mods: Vec<Modifier>,
+    hit: Strike,
+    counter: Option<Strike>,
+}
+
+impl Setup {
+    fn melee(attacker: &Unit, defender: &Unit, ctx: &CombatContext, rules: &Rules) -> Self {
+        let c = &rules.combat;
+        let att_mods = ctx.attack_modifiers(attacker, c, true);
+        let def_mods = ctx.defense_modifiers(defender, c);
+        let sa = Fixed::from_int(strength(rules, attacker)) * factor(&att_mods);
+        let sd = Fixed::from_int(strength(rules, defender)) * factor(&def_mods);
+        Self {
+            att_mods,
+            def_mods,
+            hit: Strike {
+                k: c.k_melee,
+                own: sa,
+                other: sd,
+            },
+            counter: Some(Strike {
+                k: c.k_melee,
+                own: sd,
+                other: sa,
+            }),
+        }
+    }
+
+    fn ranged(attacker: &Unit, defender: &Unit, ctx: &CombatContext, rules: &Rules) -> Self {
+        let c = &rules.combat;
+        let ranged = |u: &Unit| rules.unit(&u.kind).map_or(0, |def| def.ranged_strength);
+        let att_mods = ctx.attack_modifiers(attacker, c, false);
+        let def_mods = ctx.defense_modifiers(defender, c);
+        let ra = Fixed::from_int(ranged(attacker)) * factor(&att_mods);
+        let sd = Fixed::from_int(strength(rules, defender)) * factor(&def_mods);
+        // Return fire lands on the attacker's melee strength, scaled only by its supply
+        let sa =
+            Fixed::from_int(strength(rules, attacker)) * (Fixed::ONE + attacker.supply_penalty(c));
+        let counter = ctx.counter_fire.then(|| Strike {
+            k: c.k_ranged,
+            own: Fixed::from_int(ranged(defender)) * (Fixed::ONE + defender.supply_penalty(c)),
+            other: sa,
+        });
+        Self {
+            att_mods,
+            def_mods,
+            hit: Strike {
+                k: c.k_ranged,
+                own: ra,
+                other: sd,
+            },
+            counter,
+        }
+    }
+
+    /// Raw damage to the defender and to the attacker
+    fn raw(&self, c: &CombatConstants) -> (Fixed, Fixed) {
+        let alpha = Fixed::from_pct(c.alpha_pct);
+        let strike = |s: Strike| damage(s.k, s.own, s.other, alpha);
+        (strike(self.hit), self.counter.map_or(Fixed::ZERO, strike))
+    }
+
+    fn exchange(&self, attacker: &Unit, defender: &Unit, c: &CombatConstants) -> Exchange {
+        let (raw_def, raw_att) = self.raw(c);
+        let (to_def, d) = bank(defender.wound_bank, raw_def);
+        let (to_att, a) = bank(attacker.wound_bank, raw_att);
+        Exchange {
+            to_def,
+            to_att,
+            banks: Banks { a, d },
+        }
+    }
+}
+
+pub(crate) fn strength(rules: &Rules, unit: &Unit) -> i32 {
+    rules.unit(&unit.kind).map_or(0, |def| def.strength)
+}
+
+/// Melee exchange: both sides deal `K_melee`-scaled damage
+pub fn resolve_melee(
+    attacker: &Unit,
+    defender: &Unit,
+    ctx: &CombatContext,
+    rules: &Rules,
+) -> Exchange {
+    Setup::melee(attacker, defender, ctx, rules).exchange(attacker, defender, &rules.combat)
+}
+
+/// Ranged exchange: the attacker's ranged strength against the defender's melee
+/// strength; the defender only fires back when `ctx.counter_fire`
+pub fn resolve_ranged(
+    attacker: &Unit,
+    defender: &Unit,
+    ctx: &CombatContext,
+    rules: &Rules,
+) -> Exchange {
+    Setup::ranged(attacker, defender, ctx, rules).exchange(attacker, defender, &rules.combat)
+}
+
+/// What an attack would do, and why
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CombatPreview {
+    pub ranged: bool,
+    pub context: CombatContext,
+    /// Modifiers on the attacker's strike
+    pub attacker_modifiers: Vec<Modifier>,
+    /// Modifiers on the defender's strength
+    pub defender_modifiers: Vec<Modifier>,
+    /// Effective strengths after modifiers
+    pub attacker_strength: Fixed,
+    pub defender_strength: Fixed,
+    /// Expected damage before wound banking
+    pub expected_to_def: Fixed,
+    pub expected_to_att: Fixed,
+    /// Exactly what applying the attack now would do
+    pub exchange: Exchange,
+}
+
+/// Explain an attack by `attacker` on `target` without applying it. Works whether or
+/// not the attack is currently legal, so planners can score future positions.
+pub fn preview_combat(
+    state: &State,
+    attacker: UnitId,
+    target: UnitId,
+) -> Result<CombatPreview, SimError> {
+    let rules = state.rules()?;
+    let att = state
+        .unit(attacker)
+        .ok_or(ValidationError::UnitNotFound(attacker))?;
+    let def = state
+        .unit(target)
+        .ok_or(ValidationError::UnitNotFound(target))?;
+    let context = CombatContext::between(state, att, def)?;
+    let ranged = is_ranged(rules, att);
+    let setup = if ranged {
+        Setup::ranged(att, def, &context, rules)
+    } else {
+        Setup::melee(att, def, &context, rules)
+    };
+    let (expected_to_def, expected_to_att) = setup.raw(&rules.combat);
+    let exchange = setup.exchange(att, def, &rules.combat);
+    Ok(CombatPreview {
+        ranged,
+        context,
+        attacker_strength: setup.hit.own,
+        defender_strength: setup.hit.other,
+        attacker_modifiers: setup.att_mods,
+        defender_modifiers: setup.def_mods,
+        expected_to_def,
+        expected_to_att,
+        exchange,
+    })
+}
+
+fn is_ranged(rules: &Rules, unit: &Unit) -> bool {
+    rules.unit(&unit.kind).is_some_and(|d| d.is_ranged())
+}
+
+pub(crate) fn validate_attack(
+    state: &State,
+    player: PlayerId,
+    attacker: UnitId,
+    target: UnitId,
+) -> Result<(), SimError> {
+    let att = state
+        .unit(attacker)
+        .ok_or(ValidationError::UnitNotFound(attacker))?;
+    validation::check_owner(player, att.owner)?;
+    let def = state
+        .unit(target)
+        .ok_or(ValidationError::UnitNotFound(target))?;
+    if def.owner == att.owner {
+        return Err(ValidationError::NotHostile(target).into());
+    }
+    let rules = state.rules()?;
+    let kind = rules
+        .unit(&att.kind)
+        .filter(|d| att.is_combat() && d.strength > 0)
+        .ok_or(ValidationError::CannotAttack(attacker))?;
+    if att.moves_left < 1 {
+        return Err(ValidationError::InsufficientAP {
+            needed: 1,
+            available: att.moves_left,
+        }
+        .into());
+    }
+    let range = kind.range.max(1);
+    let distance = att.pos.distance(def.pos);
+    if distance > range {
+        return Err(ValidationError::OutOfRange { distance, range }.into());
+    }
+    Ok(())
+}
+
+/// Resolve an already validated attack and apply the damage
+pub(crate) fn apply_attack(
+    state: &mut State,
+    attacker: UnitId,
+    target: UnitId,
+    rec: &mut Recorder,
+) -> Result<(), SimError> {
+    let rules = state.rules()?;
+    let (Some(att), Some(def)) = (state.unit(attacker), state.unit(target)) else {
+        return Ok(());
+    };
+    let ctx = CombatContext::between(state, att, def)?;
+    let out = if is_ranged(rules, att) {
+        resolve_ranged(att, def, &ctx, rules)
+    } else {
+        resolve_melee(att, def, &ctx, rules)
+    };
+
+    rec.unit(state, attacker);
+    rec.unit(state, target);
+    if let Some(att) = state.units.get_mut(&attacker) {
+        att.moves_left = 0;
+        att.break_fortify();
+    }
+    take_damage(state, target, out.to_def, out.banks.d, rec);
+    take_damage(state, attacker, out.to_att, out.banks.a, rec);
+    Ok(())
+}
+
+pub(crate) fn take_damage(
+    state: &mut State,
+    id: UnitId,
+    damage: i32,
+    bank: Fixed,
+    rec: &mut Recorder,
+) {
+    let Some(unit) = state.units.get_mut(&id) else {
+        return;
+    };
+    unit.wound_bank = bank;
+    if damage <= 0 {
+        return;
+    }
+    unit.hp = (unit.hp - damage).max(0);
+    let hp = unit.hp;
+    rec.emit(Event::UnitDamaged {
+        unit: id,
+        damage,
+        hp,
+    });
+    if hp == 0 {
+        state.units.remove(&id);
+        rec.emit(Event::UnitDied { unit: id });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rules::DEFAULT_VERSION;
+    use crate::test_util::new_state;
+    use crate::{
+        apply_action, validate_action, Action, HexDir, PlayerId, Terrain, TileCoord, UnitClass,
+    };
+
+    fn rules() -> &'static Rules {
+        Rules::load(DEFAULT_VERSION).unwrap()
+    }
+
+    fn warrior(id: u64, owner: u64) -> Unit {
+        Unit::new(
+            UnitId(id),
+            PlayerId(owner),
+            "warrior",
+            UnitClass::Combat,
+            TileCoord::new(0, 0),
+            2,
+        )
+    }
+
+    fn expected_value(ctx: &CombatContext) -> Fixed {
+        // Whole damage plus the bank, i.e. the raw damage from an empty bank
+        let out = resolve_melee(&warrior(1, 0), &warrior(2, 1), ctx, rules());
+        Fixed::from_int(out.to_def) + out.banks.d
+    }
+
+    #[test]
+    fn test_symmetric_matchup_is_even() {
+        let out = resolve_melee(
+            &warrior(1, 0),
+            &warrior(2, 1),
+            &CombatContext::default(),
+            rules(),
+        );
+        assert_eq!(out.to_def, out.to_att);
+        assert_eq!(out.banks.a, out.banks.d);
+        assert_eq!(out.to_def, rules().combat.k_melee);
+    }
+
+    #[test]
+    fn test_flanking_is_monotonic_and_capped() {
+        let ev: Vec<Fixed> = (0..=4)
+            .map(|flankers| {
+                expected_value(&CombatContext {
+                    flankers,
+                    ..Default::default()
+                })
+            })
+            .collect();
+        assert!(ev.windows(2).take(3).all(|w| w[0] < w[1]), "{:?}", ev);
+        // The 4th flanker is past the +36% cap
+        assert_eq!(ev[3], ev[4]);
+    }
+
+    #[test]
+    fn test_siege_reduces_lethality() {
+        let open = expected_value(&CombatContext::default());
+        let city = expected_value(&CombatContext {
+            in_city: true,
+            ..Default::default()
+        });
+        assert!(city < open);
+
+        // Terrain, city and fortify together stop at +45%
+        let mut fortified = warrior(2, 1);
+        fortified.fortify_turns = 3;
+        let ctx = CombatContext {
+            in_city: true,
+            terrain_def_pct: 25,
+            ..Default::default()
+        };
+        let capped = resolve_melee(&warrior(1, 0), &fortified, &ctx, rules());
+        let forest = resolve_melee(
+            &warrior(1, 0),
+            &warrior(2, 1),
+            &CombatContext {
+                terrain_def_pct: 45,
+                ..Default::default()
+            },
+            rules(),
+        );
+        assert_eq!(capped, forest);
+    }
+
+    #[test]
+    fn test_wounds_bank_until_whole() {
+        let mut def = warrior(2, 1);
+        let ctx = CombatContext {
+            flankers: 1,
+            ..Default::default()
+        };
+        let first = resolve_melee(&warrior(1, 0), &def, &ctx, rules());
+        assert!(first.banks.d > Fixed::ZERO);
+        def.wound_bank = first.banks.d;
+        let second = resolve_melee(&warrior(1, 0), &def, &ctx, rules());
+        let total = Fixed::from_int(first.to_def + second.to_def) + second.banks.d;
+        assert_eq!(total, (expected_value(&ctx)).mul_int(2));
+    }
+
+    #[test]
+    fn test_ranged_counter_fire_only_in_range() {
+        let mut slinger = warrior(1, 0);
+        slinger.kind = "slinger".to_string();
+        let out = resolve_ranged(&slinger, &warrior(2, 1), &CombatContext::default(), rules());
+        assert!(out.to_def > 0);
+        assert_eq!(out.to_att, 0);
+
+        let mut archer = warrior(2, 1);
+        archer.kind = "archer".to_string();
+        let ctx = CombatContext {
+            counter_fire: true,
+            ..Default::default()
+        };
+        assert!(resolve_ranged(&slinger, &archer, &ctx, rules()).to_att > 0);
+    }
+
+    #[test]
+    fn test_preview_explains_modifiers() {
+        let mut state = new_state(8, 8);
+        state
+            .map
+            .set_terrain(TileCoord::new(3, 2), Terrain::Hills)
+            .unwrap();
+        state
+            .map
+            .set_river(TileCoord::new(2, 2), HexDir::East)
+            .unwrap();
+        let att = state
+            .spawn_unit(PlayerId(0), "warrior", TileCoord::new(2, 2))
+            .unwrap();
+        state
+            .spawn_unit(PlayerId(0), "warrior", TileCoord::new(4, 2))
+            .unwrap();
+        let def = state
+            .spawn_unit(PlayerId(1), "warrior", TileCoord::new(3, 2))
+            .unwrap();
+        state.units.get_mut(&def).unwrap().fortify_turns = 3;
+        state
+            .found_city(PlayerId(1), "Fort", TileCoord::new(3, 2))
+            .unwrap();
+
+        let preview = preview_combat(&state, att, def).unwrap();
+        let kinds = |mods: &[Modifier]| mods.iter().map(|m| m.kind).collect::<Vec<_>>();
+        assert_eq!(
+            kinds(&preview.attacker_modifiers),
+            [ModifierKind::Flank(1), ModifierKind::River]
+        );
+        // Hills 20% + city 30% + fortify 25% clamps to 45%
+        assert_eq!(
+            kinds(&preview.defender_modifiers),
+            [
+                ModifierKind::HighGround,
+                ModifierKind::Terrain,
+                ModifierKind::City,
+                ModifierKind::Fortify,
+                ModifierKind::CoverCap,
+            ]
+        );
+        assert_eq!(
+            factor(&preview.defender_modifiers),
+            Fixed::from_pct(100 + 10 + 45)
+        );
+        assert!(preview.expected_to_att > preview.expected_to_def);
+
+        // The preview matches what the attack then does
+        let hp = state.unit(def).unwrap().hp;
+        apply_action(
+            &mut state,
+            PlayerId(0),
+            Action::Attack {
+                attacker: att,
+                target: def,
+            },
+        )
+        .unwrap();
+        assert_eq!(state.unit(def).unwrap().hp, hp - preview.exchange.to_def);
+    }
+
+    #[test]
+    fn test_attack_action() {
+        let mut state = new_state(8, 8);
+        let att = state
+            .spawn_unit(PlayerId(0), "warrior", TileCoord::new(2, 2))
+            .unwrap();
+        let def = state
+            .spawn_unit(PlayerId(1), "warrior", TileCoord::new(3, 2))
+            .unwrap();
+        let far = state
+            .spawn_unit(PlayerId(1), "warrior", TileCoord::new(6, 6))
+            .unwrap();
+        let code = |state: &State, target| {
+            validate_action(
+                state,
+                PlayerId(0),
+                &Action::Attack {
+                    attacker: att,
+                    target,
+                },
+            )
+            .unwrap_err()
+            .code()
+        };
+        assert_eq!(code(&state, far), Some(24));
+        assert_eq!(code(&state, att), Some(23));
+
+        let effects = apply_action(
+            &mut state,
+            PlayerId(0),
+            Action::Attack {
+                attacker: att,
+                target: def,
+            },
+        )
+        .unwrap();
+        assert_eq!(state.unit(def).unwrap().hp, 100 - 22);
+        assert_eq!(state.unit(att).unwrap().hp, 100 - 22);
+        assert_eq!(state.unit(att).unwrap().moves_left, 0);
+        assert_eq!(effects.events.len(), 2);
+        assert_eq!(code(&state, def), Some(8));
+    }
+}
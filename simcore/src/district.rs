@@ -0,0 +1,264 @@
+//! District placement and adjacency
+//!
+//! A district goes on a passable tile within the city's workable radius that holds no
+//! city and no other district, placed or queued, and whose terrain the district's
+//! rules allow. It pays its output yield from adjacency: each rule in the district
+//! definition counts matching neighbors and pays `bonus` for every `per` of them.
+//! Other districts count wherever they are placed; queued ones do not count until built.
+//! A district whose tech is frozen for its owner stays standing but pays nothing.
+
+use crate::city::City;
+use crate::rules::{AdjacencySource, DistrictDef, Rules, YieldKind};
+use crate::{tech, CityId, PlayerId, SimError, State, TileCoord, ValidationError};
+use std::collections::BTreeMap;
+
+/// A legal spot for a district and what it would yield there
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Placement {
+    pub kind: String,
+    pub tile: TileCoord,
+    pub output: YieldKind,
+    pub amount: i32,
+}
+
+/// Check the tile-level placement rules for a district of `kind` for `city`
+pub(crate) fn check_placement(
+    state: &State,
+    rules: &Rules,
+    city: &City,
+    kind: &str,
+    def: &DistrictDef,
+    tile: TileCoord,
+) -> Result<(), ValidationError> {
+    state.check_enterable(tile)?;
+    if city.pos.distance(tile) > rules.city.workable_radius {
+        return Err(ValidationError::DistrictOutOfRange(tile));
+    }
+    if state.city_at(tile).is_some() || state.cities.values().any(|c| c.claims_tile(tile)) {
+        return Err(ValidationError::TileUnavailable(tile));
+    }
+    let terrain = state.map.tile(tile).map(|t| t.terrain);
+    if !def.terrain.is_empty() && !terrain.is_some_and(|t| def.terrain.contains(&t)) {
+        return Err(ValidationError::TerrainUnsuitable {
+            kind: kind.to_string(),
+            tile,
+        });
+    }
+    Ok(())
+}
+
+/// District kind placed on `coord`, or `None` if there is none
+fn district_at(state: &State, coord: TileCoord) -> Option<&str> {
+    state
+        .cities
+        .values()
+        .flat_map(|c| &c.districts)
+        .find(|d| d.tile == coord)
+        .map(|d| d.kind.as_str())
+}
+
+/// Adjacency bonus a district of `def` would earn on `tile`
+pub fn adjacency(state: &State, def: &DistrictDef, tile: TileCoord) -> i32 {
+    def.adjacency
+        .iter()
+        .map(|adj| {
+            let count = state
+                .map
+                .neighbors(tile)
+                .filter(|&n| match &adj.source {
+                    AdjacencySource::Terrain(t) => {
+                        state.map.tile(n).is_some_and(|tile| tile.terrain == *t)
+                    }
+                    AdjacencySource::District(kind) => district_at(state, n) == Some(kind),
+                    AdjacencySource::AnyDistrict => {
+                        district_at(state, n).is_some() || state.city_at(n).is_some()
+                    }
+                    AdjacencySource::River => state.map.river_between(tile, n),
+                })
+                .count() as i32;
+            adj.bonus * (count / adj.per)
+        })
+        .sum()
+}
+
+/// The tech behind `def` is known and not frozen for `owner`; otherwise the district
+/// stands but yields nothing
+pub(crate) fn is_active(state: &State, owner: PlayerId, def: &DistrictDef) -> bool {
+    tech::check_unlocked(state, owner, def.requires_tech.as_deref()).is_ok()
+}
+
+/// Yields from a city's placed districts
+pub fn city_output(state: &State, rules: &Rules, city: &City) -> BTreeMap<YieldKind, i32> {
+    let mut out = BTreeMap::new();
+    for district in &city.districts {
+        let Some(def) = rules.district(&district.kind) else {
+            continue;
+        };
+        if is_active(state, city.owner, def) {
+            *out.entry(def.output).or_insert(0) += adjacency(state, def, district.tile);
+        }
+    }
+    out
+}
+
+/// Every district the city could queue right now, by kind then tile, with its yield
+///
+/// Empty if the city has no free district slot or a full queue. Ownership is not
+/// checked, so callers acting for a player should only ask about that player's cities.
+pub fn placements(state: &State, id: CityId) -> Result<Vec<Placement>, SimError> {
+    let rules = state.rules()?;
+    let city = state.city(id).ok_or(ValidationError::CityNotFound(id))?;
+    if city.districts_committed() >= city.district_slots()
+        || city.queue.len() >= crate::city::MAX_QUEUE_LEN
+    {
+        return Ok(Vec::new());
+    }
+    let mut tiles = state.map.tiles_within(city.pos, rules.city.workable_radius);
+    tiles.sort();
+    let mut out = Vec::new();
+    for (kind, def) in &rules.districts {
+        if tech::check_unlocked(state, city.owner, def.requires_tech.as_deref()).is_err() {
+            continue;
+        }
+        for &tile in &tiles {
+            if check_placement(state, rules, city, kind, def, tile).is_ok() {
+                out.push(Placement {
+                    kind: kind.clone(),
+                    tile,
+                    output: def.output,
+                    amount: adjacency(state, def, tile),
+                });
+            }
+        }
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{code, new_state};
+    use crate::{validate_action, Action, District, HexDir, PlayerId, Terrain};
+
+    fn setup() -> (State, CityId) {
+        let mut state = new_state(12, 12);
+        // Every district is unlocked, so only the placement rules are under test
+        let techs = state.rules().unwrap().techs.keys().cloned();
+        state
+            .players
+            .get_mut(&PlayerId(0))
+            .unwrap()
+            .known_techs
+            .extend(techs);
+        let city = state
+            .found_city(PlayerId(0), "Jericho", TileCoord::new(5, 5))
+            .unwrap();
+        (state, city)
+    }
+
+    fn build(city: CityId, kind: &str, x: i32, y: i32) -> Action {
+        Action::BuildDistrict {
+            city,
+            kind: kind.to_string(),
+            tile: TileCoord::new(x, y),
+        }
+    }
+
+    #[test]
+    fn test_placement_rules() {
+        let (mut state, city) = setup();
+        state
+            .map
+            .set_terrain(TileCoord::new(6, 5), Terrain::Marsh)
+            .unwrap();
+        assert_eq!(code(&state, &build(city, "campus", 9, 5)), Some(37));
+        assert_eq!(code(&state, &build(city, "campus", 5, 5)), Some(17));
+        assert_eq!(
+            code(&state, &build(city, "industrial_zone", 6, 5)),
+            Some(38)
+        );
+        assert!(validate_action(&state, PlayerId(0), &build(city, "campus", 6, 5)).is_ok());
+        assert!(validate_action(&state, PlayerId(0), &build(city, "campus", 8, 5)).is_ok());
+
+        state
+            .cities
+            .get_mut(&city)
+            .unwrap()
+            .districts
+            .push(District {
+                kind: "campus".into(),
+                tile: TileCoord::new(4, 5),
+            });
+        state.cities.get_mut(&city).unwrap().population = 3;
+        assert_eq!(code(&state, &build(city, "holy_site", 4, 5)), Some(17));
+    }
+
+    #[test]
+    fn test_adjacency_counts_neighbors() {
+        let (mut state, city) = setup();
+        let rules = state.rules().unwrap();
+        let campus = rules.district("campus").unwrap();
+        let tile = TileCoord::new(6, 5);
+        // City center counts as a district, but one falls short of the 1-per-2 rule
+        assert_eq!(adjacency(&state, campus, tile), 0);
+        for coord in [TileCoord::new(7, 5), TileCoord::new(6, 4)] {
+            state.map.set_terrain(coord, Terrain::Mountain).unwrap();
+        }
+        assert_eq!(adjacency(&state, campus, tile), 2);
+        state
+            .cities
+            .get_mut(&city)
+            .unwrap()
+            .districts
+            .push(District {
+                kind: "encampment".into(),
+                tile: TileCoord::new(6, 6),
+            });
+        assert_eq!(adjacency(&state, campus, tile), 3);
+
+        let hub = rules.district("commercial_hub").unwrap();
+        state.map.set_river(tile, HexDir::East).unwrap();
+        assert_eq!(adjacency(&state, hub, tile), 3);
+
+        let zone = rules.district("industrial_zone").unwrap();
+        assert_eq!(adjacency(&state, zone, TileCoord::new(7, 6)), 1);
+        let output = city_output(&state, rules, state.city(city).unwrap());
+        assert_eq!(output.get(&YieldKind::Production), Some(&0));
+    }
+
+    #[test]
+    fn test_placements_match_validation() {
+        let (mut state, city) = setup();
+        state
+            .map
+            .set_terrain(TileCoord::new(4, 4), Terrain::Mountain)
+            .unwrap();
+        let spots = placements(&state, city).unwrap();
+        assert!(!spots.is_empty());
+        for spot in &spots {
+            let action = build(city, &spot.kind, spot.tile.x, spot.tile.y);
+            assert!(
+                validate_action(&state, PlayerId(0), &action).is_ok(),
+                "{:?}",
+                spot
+            );
+        }
+        let best = spots
+            .iter()
+            .filter(|s| s.kind == "campus")
+            .max_by_key(|s| s.amount)
+            .unwrap();
+        assert_eq!(best.amount, 1);
+
+        state
+            .cities
+            .get_mut(&city)
+            .unwrap()
+            .districts
+            .push(District {
+                kind: "campus".into(),
+                tile: TileCoord::new(6, 5),
+            });
+        assert!(placements(&state, city).unwrap().is_empty());
+    }
+}
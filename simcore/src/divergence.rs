@@ -0,0 +1,327 @@
+//! Hash trees and replay divergence location
+//!
+//! A `HashTree` mirrors the `StateDigest` components (globals and players, RNG, map chunks,
+//! units, cities) down to individual entity fields. Its root equals `state_hash`, so two trees
+//! can be walked top-down and only the differing branches descended into. Combined with
+//! a per-action hash log this pins a desync to an action index and a field.
+
+use crate::hash::{hash_value, map_chunk_hash, Globals, IncrementalHasher, StateDigest};
+use crate::{apply_action, Action, Hash128, PlayerId, State};
+use serde::{Deserialize, Serialize};
+
+/// A named node with its hash and (possibly empty) children
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HashNode {
+    pub name: String,
+    pub hash: u128,
+    pub children: Vec<HashNode>,
+}
+
+impl HashNode {
+    fn leaf(name: impl Into<String>, hash: u128) -> Self {
+        Self {
+            name: name.into(),
+            hash,
+            children: Vec::new(),
+        }
+    }
+
+    /// Node whose children are the top-level fields of a serializable value
+    fn fields<T: Serialize>(name: impl Into<String>, hash: u128, value: &T) -> Self {
+        let children = match serde_json::to_value(value) {
+            Ok(serde_json::Value::Object(fields)) => fields
+                .iter()
+                .map(|(k, v)| HashNode::leaf(k.clone(), hash_value(v).0))
+                .collect(),
+            _ => Vec::new(),
+        };
+        Self {
+            name: name.into(),
+            hash,
+            children,
+        }
+    }
+}
+
+/// Structured breakdown of a state hash
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HashTree {
+    pub root: HashNode,
+}
+
+/// How a node differs between two trees
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DiffKind {
+    Changed,
+    OnlyLeft,
+    OnlyRight,
+}
+
+/// One diverging leaf (or subtree present on only one side), e.g. `units/3/hp`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HashDiff {
+    pub path: String,
+    pub kind: DiffKind,
+}
+
+impl HashTree {
+    pub fn build(state: &State) -> Self {
+        let digest = StateDigest::compute(state);
+        let size = (state.map.width(), state.map.height());
+        let map_children = std::iter::once(HashNode::leaf("size", hash_value(&size).0))
+            .chain(
+                (0..state.map.chunk_count())
+                    .map(|i| HashNode::leaf(format!("chunk{}", i), map_chunk_hash(state, i))),
+            )
+            .collect();
+        let units = state
+            .units
+            .iter()
+            .map(|(id, u)| HashNode::fields(id.0.to_string(), hash_value(u).0, u))
+            .collect();
+        let cities = state
+            .cities
+            .iter()
+            .map(|(id, c)| HashNode::fields(id.0.to_string(), hash_value(c).0, c))
+            .collect();
+
+        let mut globals = HashNode::fields("globals", digest.globals, &Globals::of(state));
+        if let Some(node) = globals.children.iter_mut().find(|n| n.name == "players") {
+            node.children = state
+                .players
+                .iter()
+                .map(|(id, p)| HashNode::fields(id.0.to_string(), hash_value(p).0, p))
+                .collect();
+        }
+
+        let children = vec![
+            globals,
+            HashNode::fields("rng", digest.rng, &state.rng),
+            HashNode {
+                name: "map".to_string(),
+                hash: digest.map,
+                children: map_children,
+            },
+            HashNode {
+                name: "units".to_string(),
+                hash: digest.units,
+                children: units,
+            },
+            HashNode {
+                name: "cities".to_string(),
+                hash: digest.cities,
+                children: cities,
+            },
+        ];
+        Self {
+            root: HashNode {
+                name: String::new(),
+                hash: digest.root().0,
+                children,
+            },
+        }
+    }
+
+    pub fn hash(&self) -> Hash128 {
+        Hash128(self.root.hash)
+    }
+
+    /// Every diverging leaf path, descending only into branches whose hashes differ
+    pub fn diff(&self, other: &HashTree) -> Vec<HashDiff> {
+        let mut out = Vec::new();
+        diff_nodes(&self.root, &other.root, "", &mut out);
+        out
+    }
+}
+
+fn diff_nodes(a: &HashNode, b: &HashNode, path: &str, out: &mut Vec<HashDiff>) {
+    if a.hash == b.hash {
+        return;
+    }
+    if a.children.is_empty() && b.children.is_empty() {
+        out.push(HashDiff {
+            path: path.to_string(),
+            kind: DiffKind::Changed,
+        });
+        return;
+    }
+    let join = |name: &str| {
+        if path.is_empty() {
+            name.to_string()
+        } else {
+            format!("{}/{}", path, name)
+        }
+    };
+    for child in &a.children {
+        match b.children.iter().find(|c| c.name == child.name) {
+            Some(other) => diff_nodes(child, other, &join(&child.name), out),
+            None => out.push(HashDiff {
+                path: join(&child.name),
+                kind: DiffKind::OnlyLeft,
+            }),
+        }
+    }
+    for child in &b.children {
+        if !a.children.iter().any(|c| c.name == child.name) {
+            out.push(HashDiff {
+                path: join(&child.name),
+                kind: DiffKind::OnlyRight,
+            });
+        }
+    }
+}
+
+/// Where two replays first disagree
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DivergenceReport {
+    /// Actions applied when the hashes first differ (0 = the initial states differ)
+    pub after_actions: usize,
+    pub diffs: Vec<HashDiff>,
+}
+
+/// First index at which two per-step hash logs differ, found by bisection. Assumes a
+/// divergence persists once it appears. A log that is a strict prefix of the other
+/// diverges where it ends.
+pub fn first_divergence(a: &[Hash128], b: &[Hash128]) -> Option<usize> {
+    let common = a.len().min(b.len());
+    let (mut lo, mut hi) = (0, common);
+    while lo < hi {
+        let mid = lo + (hi - lo) / 2;
+        if a[mid] == b[mid] {
+            lo = mid + 1;
+        } else {
+            hi = mid;
+        }
+    }
+    (lo < common || a.len() != b.len()).then_some(lo)
+}
+
+/// Per-step hash log of a replay: entry 0 is `initial`, entry i+1 follows `actions[i]`,
+/// each submitted by the player it is paired with. Rejected actions are kept in the log,
+/// exactly as a live match would see them.
+pub fn replay_hashes(initial: &State, actions: &[(PlayerId, Action)]) -> Vec<Hash128> {
+    let mut state = initial.clone();
+    let mut hasher = IncrementalHasher::new(&state).with_verification(false);
+    let mut hashes = vec![hasher.hash()];
+    for (player, action) in actions {
+        if let Ok(effects) = apply_action(&mut state, *player, action.clone()) {
+            // Verification is off, so updates cannot fail
+            let _ = hasher.update(&state, &effects);
+        }
+        hashes.push(hasher.hash());
+    }
+    hashes
+}
+
+fn replay_to(initial: &State, actions: &[(PlayerId, Action)], steps: usize) -> State {
+    let mut state = initial.clone();
+    for (player, action) in actions.iter().take(steps) {
+        let _ = apply_action(&mut state, *player, action.clone());
+    }
+    state
+}
+
+/// Replay two (initial state, action log) pairs, bisect to the first differing step and
+/// report which entities and fields diverged there
+pub fn locate_divergence(
+    left: (&State, &[(PlayerId, Action)]),
+    right: (&State, &[(PlayerId, Action)]),
+) -> Option<DivergenceReport> {
+    let a = replay_hashes(left.0, left.1);
+    let b = replay_hashes(right.0, right.1);
+    let step = first_divergence(&a, &b)?;
+    let diffs = if step < a.len() && step < b.len() {
+        let ta = HashTree::build(&replay_to(left.0, left.1, step));
+        let tb = HashTree::build(&replay_to(right.0, right.1, step));
+        ta.diff(&tb)
+    } else {
+        // One log simply ran longer; there is no state to compare against
+        Vec::new()
+    };
+    Some(DivergenceReport {
+        after_actions: step,
+        diffs,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{state_hash, Map, PlayerId, TileCoord};
+
+    fn scenario() -> (State, crate::UnitId, crate::CityId) {
+        let mut state = State::with_seed(5);
+        state.map = Map::new(20, 20).unwrap();
+        let city = state
+            .found_city(PlayerId(0), "Gobekli", TileCoord::new(4, 4))
+            .unwrap();
+        let unit = state
+            .spawn_unit(PlayerId(0), "warrior", TileCoord::new(6, 6))
+            .unwrap();
+        (state, unit, city)
+    }
+
+    #[test]
+    fn test_tree_root_is_state_hash() {
+        let (state, _, _) = scenario();
+        assert_eq!(HashTree::build(&state).hash(), state_hash(&state));
+    }
+
+    #[test]
+    fn test_diff_names_entity_and_field() {
+        let (a, unit, _) = scenario();
+        let mut b = a.clone();
+        b.units.get_mut(&unit).unwrap().hp = 40;
+        let diffs = HashTree::build(&a).diff(&HashTree::build(&b));
+        assert_eq!(
+            diffs,
+            vec![HashDiff {
+                path: format!("units/{}/hp", unit.0),
+                kind: DiffKind::Changed,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_diff_descends_into_players() {
+        let (mut a, _, _) = scenario();
+        a.add_player(PlayerId(0), "Natufians");
+        a.add_player(PlayerId(1), "Khiamians");
+        let mut b = a.clone();
+        b.players.get_mut(&PlayerId(1)).unwrap().name = "Harifians".to_string();
+        let diffs = HashTree::build(&a).diff(&HashTree::build(&b));
+        assert_eq!(diffs.len(), 1);
+        assert_eq!(diffs[0].path, "globals/players/1/name");
+    }
+
+    #[test]
+    fn test_first_divergence_bisects() {
+        let h = |v: u128| Hash128(v);
+        let a: Vec<_> = (0..10).map(h).collect();
+        let mut b = a.clone();
+        assert_eq!(first_divergence(&a, &b), None);
+        for v in b.iter_mut().skip(6) {
+            v.0 += 100;
+        }
+        assert_eq!(first_divergence(&a, &b), Some(6));
+        assert_eq!(first_divergence(&a, &a[..4]), Some(4));
+    }
+
+    #[test]
+    fn test_locate_divergence_reports_action_index() {
+        let (mut state, _, city) = scenario();
+        state.add_player(PlayerId(0), "Natufians");
+        let build = |kind: &str| Action::BuildUnit {
+            city,
+            kind: kind.to_string(),
+        };
+        let log = |kind| [Action::EndTurn, build(kind), Action::EndTurn].map(|a| (PlayerId(0), a));
+        let (left, right) = (log("warrior"), log("slinger"));
+        assert_eq!(locate_divergence((&state, &left), (&state, &left)), None);
+
+        let report = locate_divergence((&state, &left), (&state, &right)).unwrap();
+        assert_eq!(report.after_actions, 2);
+        assert_eq!(report.diffs.len(), 1);
+        assert_eq!(report.diffs[0].path, format!("cities/{}/queue", city.0));
+    }
+}
@@ -0,0 +1,579 @@
+//! Event-sourced effects
+//!
+//! Every action and every inter-turn pass returns `Effects`: typed `Delta`s holding the
+//! before/after snapshot of each entity that changed, and semantic `Event`s describing
+//! what happened. Deltas alone are enough to rebuild the post-state from the pre-state
+//! (and back), so replays, telemetry and the match service all read from this one
+//! source. Entities are snapshotted by a `Recorder` before they are mutated.
+
+use crate::city::ProductionItem;
+use crate::tech::FreezeCause;
+use crate::{City, CityId, Player, PlayerId, Rng, SimError, State, TileCoord, Unit, UnitId};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// One state component's value before and after a change; `None` means absent
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Delta {
+    Turn {
+        before: i32,
+        after: i32,
+    },
+    Rng {
+        before: Rng,
+        after: Rng,
+    },
+    NextUnitId {
+        before: u64,
+        after: u64,
+    },
+    NextCityId {
+        before: u64,
+        after: u64,
+    },
+    Player {
+        id: PlayerId,
+        before: Option<Player>,
+        after: Option<Player>,
+    },
+    Unit {
+        id: UnitId,
+        before: Option<Unit>,
+        after: Option<Unit>,
+    },
+    City {
+        id: CityId,
+        before: Option<City>,
+        after: Option<City>,
+    },
+}
+
+/// Something that happened, for clients, telemetry and logs
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Event {
+    UnitCreated {
+        unit: UnitId,
+        owner: PlayerId,
+        kind: String,
+        pos: TileCoord,
+    },
+    UnitMoved {
+        unit: UnitId,
+        from: TileCoord,
+        to: TileCoord,
+    },
+    UnitDamaged {
+        unit: UnitId,
+        damage: i32,
+        hp: i32,
+    },
+    UnitDied {
+        unit: UnitId,
+    },
+    UnitFortified {
+        unit: UnitId,
+    },
+    CityFounded {
+        city: CityId,
+        owner: PlayerId,
+        pos: TileCoord,
+    },
+    CityGrew {
+        city: CityId,
+        population: i32,
+    },
+    CityDamaged {
+        city: CityId,
+        damage: i32,
+        wall_hp: i32,
+    },
+    CityBreached {
+        city: CityId,
+    },
+    CityStruck {
+        city: CityId,
+        target: UnitId,
+    },
+    CityCaptured {
+        city: CityId,
+        from: PlayerId,
+        to: PlayerId,
+    },
+    ProductionQueued {
+        city: CityId,
+        item: ProductionItem,
+    },
+    ProductionCompleted {
+        city: CityId,
+        item: ProductionItem,
+    },
+    ResearchStarted {
+        player: PlayerId,
+        tech: String,
+    },
+    TechResearched {
+        player: PlayerId,
+        tech: String,
+    },
+    TechFrozen {
+        player: PlayerId,
+        tech: String,
+        cause: FreezeCause,
+    },
+    TechUnfrozen {
+        player: PlayerId,
+        tech: String,
+    },
+    PolicySet {
+        player: PlayerId,
+        slot: i32,
+        id: String,
+    },
+    DealOffered {
+        id: String,
+    },
+    DealAccepted {
+        id: String,
+    },
+    DealDeclined {
+        id: String,
+    },
+    TurnEnded {
+        turn: i32,
+    },
+}
+
+impl Event {
+    /// Variant name, e.g. for `GameEvent.event_type`
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Event::UnitCreated { .. } => "UnitCreated",
+            Event::UnitMoved { .. } => "UnitMoved",
+            Event::UnitDamaged { .. } => "UnitDamaged",
+            Event::UnitDied { .. } => "UnitDied",
+            Event::UnitFortified { .. } => "UnitFortified",
+            Event::CityFounded { .. } => "CityFounded",
+            Event::CityGrew { .. } => "CityGrew",
+            Event::CityDamaged { .. } => "CityDamaged",
+            Event::CityBreached { .. } => "CityBreached",
+            Event::CityStruck { .. } => "CityStruck",
+            Event::CityCaptured { .. } => "CityCaptured",
+            Event::ProductionQueued { .. } => "ProductionQueued",
+            Event::ProductionCompleted { .. } => "ProductionCompleted",
+            Event::ResearchStarted { .. } => "ResearchStarted",
+            Event::TechResearched { .. } => "TechResearched",
+            Event::TechFrozen { .. } => "TechFrozen",
+            Event::TechUnfrozen { .. } => "TechUnfrozen",
+            Event::PolicySet { .. } => "PolicySet",
+            Event::DealOffered { .. } => "DealOffered",
+            Event::DealAccepted { .. } => "DealAccepted",
+            Event::DealDeclined { .. } => "DealDeclined",
+            Event::TurnEnded { .. } => "TurnEnded",
+        }
+    }
+}
+
+/// Effects from applying an action or running end of turn
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Effects {
+    pub deltas: Vec<Delta>,
+    pub events: Vec<Event>,
+}
+
+/// Entities mutated by an action or turn
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Touched {
+    pub units: BTreeSet<UnitId>,
+    pub cities: BTreeSet<CityId>,
+    pub players: BTreeSet<PlayerId>,
+}
+
+impl Effects {
+    /// Entities named by the deltas, for incremental hashing
+    pub fn touched(&self) -> Touched {
+        let mut touched = Touched::default();
+        for delta in &self.deltas {
+            match delta {
+                Delta::Unit { id, .. } => {
+                    touched.units.insert(*id);
+                }
+                Delta::City { id, .. } => {
+                    touched.cities.insert(*id);
+                }
+                Delta::Player { id, .. } => {
+                    touched.players.insert(*id);
+                }
+                Delta::Turn { .. }
+                | Delta::Rng { .. }
+                | Delta::NextUnitId { .. }
+                | Delta::NextCityId { .. } => {}
+            }
+        }
+        touched
+    }
+}
+
+/// Collects before-snapshots while a transition runs and turns them into `Effects`
+///
+/// Call the snapshot methods before mutating an entity. Entities created during the
+/// transition are picked up from the id counters, so they need no snapshot.
+pub(crate) struct Recorder {
+    turn: i32,
+    rng: Rng,
+    next_unit_id: u64,
+    next_city_id: u64,
+    units: BTreeMap<UnitId, Option<Unit>>,
+    cities: BTreeMap<CityId, Option<City>>,
+    players: BTreeMap<PlayerId, Option<Player>>,
+    events: Vec<Event>,
+}
+
+impl Recorder {
+    pub(crate) fn begin(state: &State) -> Self {
+        Self {
+            turn: state.turn,
+            rng: state.rng.clone(),
+            next_unit_id: state.next_unit_id,
+            next_city_id: state.next_city_id,
+            units: BTreeMap::new(),
+            cities: BTreeMap::new(),
+            players: BTreeMap::new(),
+            events: Vec::new(),
+        }
+    }
+
+    /// Snapshot every unit, city and player up front (inter-turn processing)
+    pub(crate) fn begin_all(state: &State) -> Self {
+        let mut rec = Self::begin(state);
+        rec.units = state
+            .units
+            .iter()
+            .map(|(id, u)| (*id, Some(u.clone())))
+            .collect();
+        rec.cities = state
+            .cities
+            .iter()
+            .map(|(id, c)| (*id, Some(c.clone())))
+            .collect();
+        rec.players = state
+            .players
+            .iter()
+            .map(|(id, p)| (*id, Some(p.clone())))
+            .collect();
+        rec
+    }
+
+    pub(crate) fn unit(&mut self, state: &State, id: UnitId) {
+        self.units
+            .entry(id)
+            .or_insert_with(|| state.unit(id).cloned());
+    }
+
+    pub(crate) fn city(&mut self, state: &State, id: CityId) {
+        self.cities
+            .entry(id)
+            .or_insert_with(|| state.city(id).cloned());
+    }
+
+    pub(crate) fn player(&mut self, state: &State, id: PlayerId) {
+        self.players
+            .entry(id)
+            .or_insert_with(|| state.player(id).cloned());
+    }
+
+    pub(crate) fn emit(&mut self, event: Event) {
+        self.events.push(event);
+    }
+
+    /// Diff the snapshots against `state`, keeping only components that changed
+    pub(crate) fn finish(mut self, state: &State) -> Effects {
+        for id in (self.next_unit_id..state.next_unit_id).map(UnitId) {
+            self.units.entry(id).or_insert(None);
+        }
+        for id in (self.next_city_id..state.next_city_id).map(CityId) {
+            self.cities.entry(id).or_insert(None);
+        }
+
+        let mut deltas = Vec::new();
+        if self.turn != state.turn {
+            deltas.push(Delta::Turn {
+                before: self.turn,
+                after: state.turn,
+            });
+        }
+        if self.rng != state.rng {
+            deltas.push(Delta::Rng {
+                before: self.rng,
+                after: state.rng.clone(),
+            });
+        }
+        if self.next_unit_id != state.next_unit_id {
+            deltas.push(Delta::NextUnitId {
+                before: self.next_unit_id,
+                after: state.next_unit_id,
+            });
+        }
+        if self.next_city_id != state.next_city_id {
+            deltas.push(Delta::NextCityId {
+                before: self.next_city_id,
+                after: state.next_city_id,
+            });
+        }
+        for (id, before) in self.players {
+            let after = state.player(id).cloned();
+            if before != after {
+                deltas.push(Delta::Player { id, before, after });
+            }
+        }
+        for (id, before) in self.units {
+            let after = state.unit(id).cloned();
+            if before != after {
+                deltas.push(Delta::Unit { id, before, after });
+            }
+        }
+        for (id, before) in self.cities {
+            let after = state.city(id).cloned();
+            if before != after {
+                deltas.push(Delta::City { id, before, after });
+            }
+        }
+        Effects {
+            deltas,
+            events: self.events,
+        }
+    }
+}
+
+/// Replay `effects` onto the state they were recorded from, without validation or RNG
+///
+/// Each delta's `before` must match the current value, so a stale or out-of-order log
+/// is reported as an invariant violation instead of silently corrupting the state. On
+/// error the state may be partially updated.
+pub fn apply_effects(state: &mut State, effects: &Effects) -> Result<(), SimError> {
+    for delta in &effects.deltas {
+        set(state, delta, Side::After)?;
+    }
+    Ok(())
+}
+
+/// Undo `effects` on the state they produced, restoring the pre-state
+pub fn revert_effects(state: &mut State, effects: &Effects) -> Result<(), SimError> {
+    for delta in effects.deltas.iter().rev() {
+        set(state, delta, Side::Before)?;
+    }
+    Ok(())
+}
+
+#[derive(Clone, Copy)]
+enum Side {
+    Before,
+    After,
+}
+
+impl Side {
+    /// (expected current value, value to write)
+    fn pick<'a, T>(self, before: &'a T, after: &'a T) -> (&'a T, &'a T) {
+        match self {
+            Side::After => (before, after),
+            Side::Before => (after, before),
+        }
+    }
+}
+
+fn check<T: PartialEq>(what: &str, current: &T, expected: &T) -> Result<(), SimError> {
+    if current != expected {
+        return Err(SimError::InvariantViolation(format!(
+            "effects do not apply: {} differs from the recorded value",
+            what
+        )));
+    }
+    Ok(())
+}
+
+/// Write one side of `delta` into `state` after checking the other side is current
+fn set(state: &mut State, delta: &Delta, side: Side) -> Result<(), SimError> {
+    match delta {
+        Delta::Turn { before, after } => {
+            let (expect, value) = side.pick(before, after);
+            check("turn", &state.turn, expect)?;
+            state.turn = *value;
+        }
+        Delta::Rng { before, after } => {
+            let (expect, value) = side.pick(before, after);
+            check("rng", &state.rng, expect)?;
+            state.rng = value.clone();
+        }
+        Delta::NextUnitId { before, after } => {
+            let (expect, value) = side.pick(before, after);
+            check("next unit id", &state.next_unit_id, expect)?;
+            state.next_unit_id = *value;
+        }
+        Delta::NextCityId { before, after } => {
+            let (expect, value) = side.pick(before, after);
+            check("next city id", &state.next_city_id, expect)?;
+            state.next_city_id = *value;
+        }
+        Delta::Player { id, before, after } => {
+            let (expect, value) = side.pick(before, after);
+            check(
+                &format!("player {:?}", id),
+                &state.players.get(id).cloned(),
+                expect,
+            )?;
+            put(&mut state.players, *id, value);
+        }
+        Delta::Unit { id, before, after } => {
+            let (expect, value) = side.pick(before, after);
+            check(
+                &format!("unit {:?}", id),
+                &state.units.get(id).cloned(),
+                expect,
+            )?;
+            put(&mut state.units, *id, value);
+        }
+        Delta::City { id, before, after } => {
+            let (expect, value) = side.pick(before, after);
+            check(
+                &format!("city {:?}", id),
+                &state.cities.get(id).cloned(),
+                expect,
+            )?;
+            put(&mut state.cities, *id, value);
+        }
+    }
+    Ok(())
+}
+
+fn put<K: Ord, V: Clone>(map: &mut BTreeMap<K, V>, key: K, value: &Option<V>) {
+    match value {
+        Some(v) => {
+            map.insert(key, v.clone());
+        }
+        None => {
+            map.remove(&key);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::new_state;
+    use crate::{apply_action, state_hash, Action, Map};
+
+    #[test]
+    fn test_build_order_records_city_delta_and_event() {
+        let mut state = new_state(6, 6);
+        let city = state
+            .found_city(PlayerId(0), "Jericho", TileCoord::new(2, 2))
+            .unwrap();
+        let before = state.city(city).cloned();
+        let effects = apply_action(
+            &mut state,
+            PlayerId(0),
+            Action::BuildUnit {
+                city,
+                kind: "warrior".to_string(),
+            },
+        )
+        .unwrap();
+        assert_eq!(
+            effects.deltas,
+            vec![Delta::City {
+                id: city,
+                before,
+                after: state.city(city).cloned(),
+            }]
+        );
+        assert_eq!(effects.events[0].kind(), "ProductionQueued");
+        assert_eq!(
+            effects.touched().cities.into_iter().collect::<Vec<_>>(),
+            vec![city]
+        );
+    }
+
+    #[test]
+    fn test_end_turn_reports_new_units() {
+        let mut state = new_state(6, 6);
+        let pos = TileCoord::new(2, 2);
+        let city = state.found_city(PlayerId(0), "Jericho", pos).unwrap();
+        state.cities.get_mut(&city).unwrap().production_stored = crate::Fixed::from_int(50);
+        apply_action(
+            &mut state,
+            PlayerId(0),
+            Action::BuildUnit {
+                city,
+                kind: "warrior".to_string(),
+            },
+        )
+        .unwrap();
+
+        let effects = apply_action(&mut state, PlayerId(0), Action::EndTurn).unwrap();
+        let unit = state
+            .unit_of_class_at(pos, crate::UnitClass::Combat)
+            .unwrap();
+        assert!(effects.deltas.contains(&Delta::Unit {
+            id: unit.id,
+            before: None,
+            after: Some(unit.clone()),
+        }));
+        assert!(effects.deltas.contains(&Delta::Turn {
+            before: 0,
+            after: 1
+        }));
+        let kinds: Vec<_> = effects.events.iter().map(Event::kind).collect();
+        assert_eq!(kinds, ["UnitCreated", "ProductionCompleted", "TurnEnded"]);
+
+        let json = serde_json::to_string(&effects).unwrap();
+        assert_eq!(serde_json::from_str::<Effects>(&json).unwrap(), effects);
+    }
+
+    #[test]
+    fn test_apply_and_revert_round_trip() {
+        let mut state = State::with_seed(3);
+        state.map = Map::new(8, 8).unwrap();
+        state.add_player(PlayerId(0), "Natufians");
+        let city = state
+            .found_city(PlayerId(0), "Jericho", TileCoord::new(3, 3))
+            .unwrap();
+        let start = state.clone();
+        let mut live = state.clone();
+
+        let actions = [
+            Action::BuildUnit {
+                city,
+                kind: "slinger".to_string(),
+            },
+            Action::EndTurn,
+            Action::EndTurn,
+            Action::EndTurn,
+        ];
+        let log: Vec<Effects> = actions
+            .iter()
+            .map(|a| apply_action(&mut live, PlayerId(0), a.clone()).unwrap())
+            .collect();
+
+        // Rebuild from the log alone
+        for effects in &log {
+            apply_effects(&mut state, effects).unwrap();
+        }
+        assert_eq!(state_hash(&state), state_hash(&live));
+
+        // Undo back to the start
+        for effects in log.iter().rev() {
+            revert_effects(&mut state, effects).unwrap();
+        }
+        assert_eq!(state_hash(&state), state_hash(&start));
+    }
+
+    #[test]
+    fn test_stale_log_is_rejected() {
+        let mut state = State::new();
+        let effects = crate::end_turn_with_effects(&mut state.clone()).unwrap();
+        state.turn = 5;
+        assert!(matches!(
+            apply_effects(&mut state, &effects),
+            Err(SimError::InvariantViolation(_))
+        ));
+    }
+}
@@ -0,0 +1,330 @@
+//! Fixed-point arithmetic for gameplay math
+//!
+//! `Fixed` is a signed Q47.16 number stored in an `i64`. Every operation saturates
+//! instead of overflowing, and multiply/divide round to nearest with ties away from
+//! zero, so results are bit-identical on every target. It serializes as its raw integer.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::ops::{Add, AddAssign, Div, Mul, Neg, Sub, SubAssign};
+
+/// Fractional bits
+pub const FRAC_BITS: u32 = 16;
+
+const ONE_RAW: i64 = 1 << FRAC_BITS;
+
+/// Signed fixed-point number with 16 fractional bits
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+#[serde(transparent)]
+pub struct Fixed(i64);
+
+/// `n / d` rounded to nearest, ties away from zero; `d` must be non-zero
+fn div_round(n: i128, d: i128) -> i128 {
+    let (q, r) = (n / d, n % d);
+    if 2 * r.abs() >= d.abs() {
+        q + if (n < 0) == (d < 0) { 1 } else { -1 }
+    } else {
+        q
+    }
+}
+
+/// Internal precision for `log2`/`exp2`: Q2.62 in a `u64`
+const WIDE_BITS: u32 = 62;
+
+/// `2^(2^-i)` in Q2.62 for `i` in `1..=FRAC_BITS`
+const EXP2_TABLE: [u64; FRAC_BITS as usize] = [
+    0x5a827999fcef3242,
+    0x4c1bf828c6dc54b8,
+    0x45cae0f1f545eb73,
+    0x42d561b3e6243d8a,
+    0x4166c34c5615d0ec,
+    0x40b268f9de0183ba,
+    0x4058f6a7ecccd5b6,
+    0x402c6be96af2fb58,
+    0x4016321b687027a8,
+    0x400b18178ba33b14,
+    0x40058bce410147e8,
+    0x4002c5d7bff71daf,
+    0x400162e807ee7e5b,
+    0x4000b1730df6a524,
+    0x400058b9497b8152,
+    0x40002c5c955dd701,
+];
+
+fn saturate(v: i128) -> Fixed {
+    Fixed(v.clamp(i64::MIN as i128, i64::MAX as i128) as i64)
+}
+
+impl Fixed {
+    pub const ZERO: Fixed = Fixed(0);
+    pub const ONE: Fixed = Fixed(ONE_RAW);
+    pub const HALF: Fixed = Fixed(ONE_RAW / 2);
+    pub const MAX: Fixed = Fixed(i64::MAX);
+    pub const MIN: Fixed = Fixed(i64::MIN);
+
+    pub const fn from_raw(raw: i64) -> Self {
+        Self(raw)
+    }
+
+    pub const fn raw(self) -> i64 {
+        self.0
+    }
+
+    pub const fn from_int(v: i32) -> Self {
+        Self((v as i64) << FRAC_BITS)
+    }
+
+    /// `num / den`, rounded; a zero denominator saturates toward the numerator's sign
+    pub fn from_ratio(num: i64, den: i64) -> Self {
+        if den == 0 {
+            return match num.signum() {
+                1 => Self::MAX,
+                -1 => Self::MIN,
+                _ => Self::ZERO,
+            };
+        }
+        saturate(div_round((num as i128) << FRAC_BITS, den as i128))
+    }
+
+    /// A percentage as a fraction, e.g. `from_pct(25)` is 0.25
+    pub fn from_pct(pct: i32) -> Self {
+        Self::from_ratio(pct as i64, 100)
+    }
+
+    /// Largest integer not above the value, saturated to `i32`
+    pub fn floor(self) -> i32 {
+        (self.0 >> FRAC_BITS).clamp(i32::MIN as i64, i32::MAX as i64) as i32
+    }
+
+    /// Nearest integer, ties away from zero, saturated to `i32`
+    pub fn round(self) -> i32 {
+        div_round(self.0 as i128, ONE_RAW as i128).clamp(i32::MIN as i128, i32::MAX as i128) as i32
+    }
+
+    /// The part above `floor`, always in `[0, 1)`
+    pub fn frac(self) -> Fixed {
+        Fixed(self.0 & (ONE_RAW - 1))
+    }
+
+    pub fn abs(self) -> Fixed {
+        Fixed(self.0.saturating_abs())
+    }
+
+    pub fn saturating_add(self, rhs: Fixed) -> Fixed {
+        Fixed(self.0.saturating_add(rhs.0))
+    }
+
+    pub fn saturating_sub(self, rhs: Fixed) -> Fixed {
+        Fixed(self.0.saturating_sub(rhs.0))
+    }
+
+    pub fn saturating_mul(self, rhs: Fixed) -> Fixed {
+        saturate(div_round(self.0 as i128 * rhs.0 as i128, ONE_RAW as i128))
+    }
+
+    /// Division by zero saturates toward the dividend's sign
+    pub fn saturating_div(self, rhs: Fixed) -> Fixed {
+        if rhs.0 == 0 {
+            return Fixed::from_ratio(self.0, 0);
+        }
+        saturate(div_round((self.0 as i128) << FRAC_BITS, rhs.0 as i128))
+    }
+
+    pub fn mul_int(self, rhs: i32) -> Fixed {
+        saturate(self.0 as i128 * rhs as i128)
+    }
+
+    /// Scale by a percentage, e.g. `x.mul_pct(110)` is +10%
+    pub fn mul_pct(self, pct: i32) -> Fixed {
+        saturate(div_round(self.0 as i128 * pct as i128, 100))
+    }
+
+    /// Base-2 logarithm, truncated to the last fractional bit; `None` unless positive
+    pub fn log2(self) -> Option<Fixed> {
+        if self.0 <= 0 {
+            return None;
+        }
+        let msb = 63 - self.0.leading_zeros();
+        let int = msb as i64 - FRAC_BITS as i64;
+        // Mantissa in [1, 2), then one result bit per squaring
+        let mut m = (self.0 as u64) << (WIDE_BITS - msb);
+        let mut frac = 0i64;
+        for _ in 0..FRAC_BITS {
+            m = ((m as u128 * m as u128) >> WIDE_BITS) as u64;
+            frac <<= 1;
+            if m >= 2 << WIDE_BITS {
+                m >>= 1;
+                frac |= 1;
+            }
+        }
+        Some(Fixed((int << FRAC_BITS) | frac))
+    }
+
+    /// Two to the power of `self`, rounded; saturates above `MAX`
+    pub fn exp2(self) -> Fixed {
+        let int = self.0 >> FRAC_BITS;
+        let frac = self.0 & (ONE_RAW - 1);
+        let mut acc: u64 = 1 << WIDE_BITS;
+        for (i, factor) in EXP2_TABLE.iter().enumerate() {
+            if frac & (1 << (FRAC_BITS as usize - 1 - i)) != 0 {
+                acc = ((acc as u128 * *factor as u128) >> WIDE_BITS) as u64;
+            }
+        }
+        // acc is 2^frac in Q2.62; shift to Q47.16 scaled by 2^int
+        let shift = int + FRAC_BITS as i64 - WIDE_BITS as i64;
+        if shift >= 0 {
+            if shift > 62 {
+                return Fixed::MAX;
+            }
+            saturate((acc as i128) << shift)
+        } else if shift < -64 {
+            Fixed::ZERO
+        } else {
+            saturate(div_round(acc as i128, 1i128 << -shift))
+        }
+    }
+
+    /// `self` raised to `exp`; `None` unless `self` is positive
+    pub fn pow(self, exp: Fixed) -> Option<Fixed> {
+        Some((self.log2()? * exp).exp2())
+    }
+}
+
+impl From<i32> for Fixed {
+    fn from(v: i32) -> Self {
+        Fixed::from_int(v)
+    }
+}
+
+impl Add for Fixed {
+    type Output = Fixed;
+    fn add(self, rhs: Fixed) -> Fixed {
+        self.saturating_add(rhs)
+    }
+}
+
+impl Sub for Fixed {
+    type Output = Fixed;
+    fn sub(self, rhs: Fixed) -> Fixed {
+        self.saturating_sub(rhs)
+    }
+}
+
+impl Mul for Fixed {
+    type Output = Fixed;
+    fn mul(self, rhs: Fixed) -> Fixed {
+        self.saturating_mul(rhs)
+    }
+}
+
+impl Div for Fixed {
+    type Output = Fixed;
+    fn div(self, rhs: Fixed) -> Fixed {
+        self.saturating_div(rhs)
+    }
+}
+
+impl Neg for Fixed {
+    type Output = Fixed;
+    fn neg(self) -> Fixed {
+        Fixed(self.0.saturating_neg())
+    }
+}
+
+impl AddAssign for Fixed {
+    fn add_assign(&mut self, rhs: Fixed) {
+        *self = *self + rhs;
+    }
+}
+
+impl SubAssign for Fixed {
+    fn sub_assign(&mut self, rhs: Fixed) {
+        *self = *self - rhs;
+    }
+}
+
+impl fmt::Display for Fixed {
+    /// Exact decimal rendering to four places (rounded)
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let ten_thousandths = div_round(self.0 as i128 * 10_000, ONE_RAW as i128);
+        let sign = if ten_thousandths < 0 { "-" } else { "" };
+        let v = ten_thousandths.abs();
+        write!(f, "{}{}.{:04}", sign, v / 10_000, v % 10_000)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rounding_is_to_nearest_ties_away() {
+        assert_eq!(Fixed::from_ratio(1, 2), Fixed::HALF);
+        assert_eq!(Fixed::from_raw(ONE_RAW + ONE_RAW / 2).round(), 2);
+        assert_eq!(Fixed::from_raw(-ONE_RAW - ONE_RAW / 2).round(), -2);
+        assert_eq!(Fixed::from_raw(ONE_RAW + ONE_RAW / 2 - 1).round(), 1);
+        // One raw unit times a half is exactly a tie
+        assert_eq!(Fixed::from_raw(1) * Fixed::HALF, Fixed::from_raw(1));
+        assert_eq!(Fixed::from_raw(-1) * Fixed::HALF, Fixed::from_raw(-1));
+        assert_eq!(Fixed::from_int(-3).floor(), -3);
+        assert_eq!((Fixed::from_int(-3) + Fixed::from_pct(50)).floor(), -3);
+        assert_eq!((Fixed::from_int(-3) - Fixed::from_pct(50)).floor(), -4);
+    }
+
+    #[test]
+    fn test_arithmetic() {
+        let a = Fixed::from_int(7);
+        let b = Fixed::from_int(2);
+        assert_eq!(a / b, Fixed::from_ratio(7, 2));
+        assert_eq!(a * b, Fixed::from_int(14));
+        assert_eq!(a.mul_pct(110), Fixed::from_ratio(77, 10));
+        assert_eq!(Fixed::from_ratio(7, 2).frac(), Fixed::HALF);
+        assert_eq!(Fixed::from_ratio(-7, 2).frac(), Fixed::HALF);
+        assert_eq!(Fixed::from_pct(-30).to_string(), "-0.3000");
+        assert_eq!(Fixed::from_ratio(1, 3).to_string(), "0.3333");
+    }
+
+    #[test]
+    fn test_saturates_instead_of_overflowing() {
+        assert_eq!(Fixed::MAX + Fixed::ONE, Fixed::MAX);
+        assert_eq!(Fixed::MIN - Fixed::ONE, Fixed::MIN);
+        assert_eq!(Fixed::MAX * Fixed::from_int(2), Fixed::MAX);
+        assert_eq!(-Fixed::MIN, Fixed::MAX);
+        assert_eq!(Fixed::ONE / Fixed::ZERO, Fixed::MAX);
+        assert_eq!(-Fixed::ONE / Fixed::ZERO, Fixed::MIN);
+        assert_eq!(Fixed::MAX.floor(), i32::MAX);
+    }
+
+    #[test]
+    fn test_log2_exp2_pow() {
+        assert_eq!(Fixed::ONE.log2(), Some(Fixed::ZERO));
+        assert_eq!(Fixed::from_int(8).log2(), Some(Fixed::from_int(3)));
+        assert_eq!(Fixed::from_ratio(1, 4).log2(), Some(Fixed::from_int(-2)));
+        assert_eq!(Fixed::ZERO.log2(), None);
+        assert_eq!(Fixed::from_int(-3).exp2(), Fixed::from_ratio(1, 8));
+        assert_eq!(Fixed::from_int(10).exp2(), Fixed::from_int(1024));
+        assert_eq!(Fixed::from_int(100).exp2(), Fixed::MAX);
+
+        let close = |a: Fixed, b: Fixed| (a - b).abs() <= Fixed::from_raw(4);
+        assert!(close(Fixed::HALF.exp2(), Fixed::from_ratio(92_682, 65_536)));
+        assert!(close(
+            Fixed::from_int(2).pow(Fixed::HALF).unwrap(),
+            Fixed::from_ratio(92_682, 65_536)
+        ));
+        assert!(close(
+            Fixed::from_int(9).pow(Fixed::HALF).unwrap(),
+            Fixed::from_int(3)
+        ));
+        assert_eq!(Fixed::ONE.pow(Fixed::from_pct(37)), Some(Fixed::ONE));
+    }
+
+    #[test]
+    fn test_serializes_as_raw_bits() {
+        let x = Fixed::from_ratio(5, 4);
+        let json = serde_json::to_string(&x).unwrap();
+        assert_eq!(json, (5 * ONE_RAW / 4).to_string());
+        assert_eq!(serde_json::from_str::<Fixed>(&json).unwrap(), x);
+    }
+}
@@ -0,0 +1,73 @@
+//! Fog of war
+//!
+//! A player sees every tile within `unit_sight` of one of their living units and within
+//! `city_sight` of one of their cities. Terrain is treated as known everywhere; only
+//! other players' units are hidden outside the visible set.
+
+use crate::{PlayerId, SimError, State, TileCoord, Unit};
+use std::collections::BTreeSet;
+
+/// Tiles `player` currently sees
+pub fn visible_tiles(state: &State, player: PlayerId) -> Result<BTreeSet<TileCoord>, SimError> {
+    let vision = &state.rules()?.vision;
+    let mut visible = BTreeSet::new();
+    for unit in state.units.values() {
+        if unit.owner == player && unit.is_alive() {
+            visible.extend(state.map.tiles_within(unit.pos, vision.unit_sight));
+        }
+    }
+    for city in state.cities.values() {
+        if city.owner == player {
+            visible.extend(state.map.tiles_within(city.pos, vision.city_sight));
+        }
+    }
+    Ok(visible)
+}
+
+/// Units `player` knows about: their own plus everyone else's on visible tiles
+pub fn known_units<'a>(
+    state: &'a State,
+    player: PlayerId,
+    visible: &'a BTreeSet<TileCoord>,
+) -> impl Iterator<Item = &'a Unit> + 'a {
+    state
+        .units
+        .values()
+        .filter(move |u| u.owner == player || visible.contains(&u.pos))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Map;
+
+    #[test]
+    fn test_sight_from_units_and_cities() {
+        let mut state = State::new();
+        state.map = Map::new(20, 20).unwrap();
+        state
+            .spawn_unit(PlayerId(0), "warrior", TileCoord::new(2, 2))
+            .unwrap();
+        state
+            .found_city(PlayerId(0), "Home", TileCoord::new(12, 12))
+            .unwrap();
+        let far = state
+            .spawn_unit(PlayerId(1), "warrior", TileCoord::new(18, 2))
+            .unwrap();
+        let near = state
+            .spawn_unit(PlayerId(1), "warrior", TileCoord::new(4, 2))
+            .unwrap();
+
+        let visible = visible_tiles(&state, PlayerId(0)).unwrap();
+        assert!(visible.contains(&TileCoord::new(4, 2)));
+        assert!(!visible.contains(&TileCoord::new(5, 2)));
+        assert!(visible.contains(&TileCoord::new(15, 12)));
+        assert!(!visible.contains(&TileCoord::new(16, 12)));
+
+        let known: Vec<_> = known_units(&state, PlayerId(0), &visible)
+            .map(|u| u.id)
+            .collect();
+        assert!(known.contains(&near));
+        assert!(!known.contains(&far));
+    }
+}
@@ -0,0 +1,629 @@
+//! Canonical state encoding and xxh3-128 hashing
+//!
+//! `canonical_bytes` drives any `Serialize` value through a minimal serde serializer that
+//! writes a platform-independent byte stream: fixed-width little-endian integers, u64
+//! length prefixes, enum variants by index, and map entries sorted by their encoded key.
+//!
+//! The state hash is built from component sub-hashes (`StateDigest`): scalar globals, the
+//! RNG, map chunks, and one hash per unit/city folded together with wrapping addition so
+//! entity order never matters. `IncrementalHasher` keeps those sub-hashes up to date from
+//! the entities an action touched instead of re-encoding the whole state. The golden
+//! vectors in `tests/state_hash_golden.rs` pin the result.
+
+use crate::{CityId, Effects, Hash128, Player, PlayerId, SimError, State, UnitId};
+use serde::ser;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt;
+use xxhash_rust::xxh3::xxh3_128;
+
+/// Canonical encoding error (only raised by custom `Serialize` impls)
+#[derive(Debug)]
+pub struct EncodeError(String);
+
+impl fmt::Display for EncodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "canonical encoding failed: {}", self.0)
+    }
+}
+
+impl std::error::Error for EncodeError {}
+
+impl ser::Error for EncodeError {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        EncodeError(msg.to_string())
+    }
+}
+
+/// Encode a value into its canonical byte form
+pub fn canonical_bytes<T: Serialize + ?Sized>(value: &T) -> Result<Vec<u8>, EncodeError> {
+    let mut encoder = Encoder { out: Vec::new() };
+    value.serialize(&mut encoder)?;
+    Ok(encoder.out)
+}
+
+/// xxh3-128 of a value's canonical encoding
+pub fn hash_value<T: Serialize + ?Sized>(value: &T) -> Hash128 {
+    let bytes = canonical_bytes(value).expect("simcore types encode infallibly");
+    Hash128(xxh3_128(&bytes))
+}
+
+/// Scalar state fields not covered by another component
+#[derive(Serialize)]
+pub(crate) struct Globals<'a> {
+    turn: i32,
+    rules_ver: &'a str,
+    next_unit_id: u64,
+    next_city_id: u64,
+    players: &'a BTreeMap<PlayerId, Player>,
+}
+
+impl<'a> Globals<'a> {
+    pub(crate) fn of(state: &'a State) -> Self {
+        Self {
+            turn: state.turn,
+            rules_ver: &state.rules_ver,
+            next_unit_id: state.next_unit_id,
+            next_city_id: state.next_city_id,
+            players: &state.players,
+        }
+    }
+}
+
+fn sum(hashes: impl Iterator<Item = u128>) -> u128 {
+    hashes.fold(0, u128::wrapping_add)
+}
+
+pub(crate) fn map_chunk_hash(state: &State, index: usize) -> u128 {
+    hash_value(&(index as u64, state.map.chunk_tiles(index))).0
+}
+
+fn map_hash(width: i32, height: i32, chunks: &[u128]) -> u128 {
+    hash_value(&(width, height, chunks)).0
+}
+
+/// Per-component sub-hashes whose combination is the state hash
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct StateDigest {
+    pub globals: u128,
+    pub rng: u128,
+    pub map: u128,
+    /// Order-independent fold of every unit's hash
+    pub units: u128,
+    /// Order-independent fold of every city's hash
+    pub cities: u128,
+}
+
+impl StateDigest {
+    /// Full recomputation from scratch
+    pub fn compute(state: &State) -> Self {
+        let chunks: Vec<u128> = (0..state.map.chunk_count())
+            .map(|i| map_chunk_hash(state, i))
+            .collect();
+        Self {
+            globals: hash_value(&Globals::of(state)).0,
+            rng: hash_value(&state.rng).0,
+            map: map_hash(state.map.width(), state.map.height(), &chunks),
+            units: sum(state.units.values().map(|u| hash_value(u).0)),
+            cities: sum(state.cities.values().map(|c| hash_value(c).0)),
+        }
+    }
+
+    pub fn root(&self) -> Hash128 {
+        hash_value(self)
+    }
+
+    /// Names of the components that differ between two digests
+    pub fn differing(&self, other: &StateDigest) -> Vec<&'static str> {
+        let pairs = [
+            ("globals", self.globals, other.globals),
+            ("rng", self.rng, other.rng),
+            ("map", self.map, other.map),
+            ("units", self.units, other.units),
+            ("cities", self.cities, other.cities),
+        ];
+        pairs
+            .into_iter()
+            .filter(|(_, a, b)| a != b)
+            .map(|(name, _, _)| name)
+            .collect()
+    }
+}
+
+/// State hash maintained incrementally from `Effects::touched`
+///
+/// With verification on (the default in debug builds) every update is cross-checked
+/// against a full `StateDigest::compute` and drift is reported as an invariant violation.
+#[derive(Debug, Clone)]
+pub struct IncrementalHasher {
+    digest: StateDigest,
+    units: BTreeMap<UnitId, u128>,
+    cities: BTreeMap<CityId, u128>,
+    chunks: Vec<u128>,
+    verify: bool,
+}
+
+impl IncrementalHasher {
+    pub fn new(state: &State) -> Self {
+        let mut hasher = Self {
+            digest: StateDigest::compute(state),
+            units: BTreeMap::new(),
+            cities: BTreeMap::new(),
+            chunks: Vec::new(),
+            verify: cfg!(debug_assertions),
+        };
+        hasher.rebuild(state);
+        hasher
+    }
+
+    /// Turn full-recompute cross-checking on or off
+    pub fn with_verification(mut self, verify: bool) -> Self {
+        self.verify = verify;
+        self
+    }
+
+    pub fn digest(&self) -> StateDigest {
+        self.digest
+    }
+
+    pub fn hash(&self) -> Hash128 {
+        self.digest.root()
+    }
+
+    fn rebuild(&mut self, state: &State) {
+        self.units = state
+            .units
+            .iter()
+            .map(|(id, u)| (*id, hash_value(u).0))
+            .collect();
+        self.cities = state
+            .cities
+            .iter()
+            .map(|(id, c)| (*id, hash_value(c).0))
+            .collect();
+        self.chunks = (0..state.map.chunk_count())
+            .map(|i| map_chunk_hash(state, i))
+            .collect();
+        self.digest = StateDigest::compute(state);
+    }
+
+    /// Fold the entities named by `effects` back into the digest. `state` must be the
+    /// state the effects were just applied to.
+    pub fn update(&mut self, state: &State, effects: &Effects) -> Result<Hash128, SimError> {
+        let touched = effects.touched();
+        if self.chunks.len() != state.map.chunk_count() {
+            self.rebuild(state);
+        } else {
+            for id in &touched.units {
+                let old = self.units.remove(id).unwrap_or(0);
+                let new = state.unit(*id).map(|u| hash_value(u).0);
+                if let Some(h) = new {
+                    self.units.insert(*id, h);
+                }
+                self.digest.units = self
+                    .digest
+                    .units
+                    .wrapping_sub(old)
+                    .wrapping_add(new.unwrap_or(0));
+            }
+            for id in &touched.cities {
+                let old = self.cities.remove(id).unwrap_or(0);
+                let new = state.city(*id).map(|c| hash_value(c).0);
+                if let Some(h) = new {
+                    self.cities.insert(*id, h);
+                }
+                self.digest.cities = self
+                    .digest
+                    .cities
+                    .wrapping_sub(old)
+                    .wrapping_add(new.unwrap_or(0));
+            }
+            // Actions never change map tiles, so the map sub-hash only moves on a rebuild
+            // Globals and RNG are small enough to rehash on every update
+            self.digest.globals = hash_value(&Globals::of(state)).0;
+            self.digest.rng = hash_value(&state.rng).0;
+        }
+
+        if self.verify {
+            let full = StateDigest::compute(state);
+            if full != self.digest {
+                return Err(SimError::InvariantViolation(format!(
+                    "incremental hash drift in {:?}",
+                    self.digest.differing(&full)
+                )));
+            }
+        }
+        Ok(self.hash())
+    }
+}
+
+struct Encoder {
+    out: Vec<u8>,
+}
+
+impl Encoder {
+    fn len(&mut self, len: usize) {
+        self.out.extend_from_slice(&(len as u64).to_le_bytes());
+    }
+
+    fn variant(&mut self, index: u32) {
+        self.out.extend_from_slice(&index.to_le_bytes());
+    }
+}
+
+/// Sequence/map builder: elements are encoded into side buffers so the length prefix
+/// does not depend on serde's size hint and map entries can be sorted before writing
+struct Collect<'a> {
+    parent: &'a mut Encoder,
+    items: Vec<Vec<u8>>,
+    pending_key: Option<Vec<u8>>,
+    sort: bool,
+}
+
+impl<'a> Collect<'a> {
+    fn new(parent: &'a mut Encoder, sort: bool) -> Self {
+        Self {
+            parent,
+            items: Vec::new(),
+            pending_key: None,
+            sort,
+        }
+    }
+
+    fn push<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), EncodeError> {
+        self.items.push(canonical_bytes(value)?);
+        Ok(())
+    }
+
+    fn finish(mut self) -> Result<(), EncodeError> {
+        if self.sort {
+            self.items.sort();
+        }
+        self.parent.len(self.items.len());
+        for item in self.items {
+            self.parent.out.extend_from_slice(&item);
+        }
+        Ok(())
+    }
+}
+
+macro_rules! encode_le {
+    ($($method:ident: $ty:ty),*) => {
+        $(fn $method(self, v: $ty) -> Result<(), EncodeError> {
+            self.out.extend_from_slice(&v.to_le_bytes());
+            Ok(())
+        })*
+    };
+}
+
+impl<'a> ser::Serializer for &'a mut Encoder {
+    type Ok = ();
+    type Error = EncodeError;
+    type SerializeSeq = Collect<'a>;
+    type SerializeTuple = Self;
+    type SerializeTupleStruct = Self;
+    type SerializeTupleVariant = Self;
+    type SerializeMap = Collect<'a>;
+    type SerializeStruct = Self;
+    type SerializeStructVariant = Self;
+
+    encode_le!(
+        serialize_i8: i8, serialize_i16: i16, serialize_i32: i32, serialize_i64: i64,
+        serialize_i128: i128, serialize_u8: u8, serialize_u16: u16, serialize_u32: u32,
+        serialize_u64: u64, serialize_u128: u128
+    );
+
+    fn serialize_bool(self, v: bool) -> Result<(), EncodeError> {
+        self.out.push(v as u8);
+        Ok(())
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<(), EncodeError> {
+        self.serialize_u32(v.to_bits())
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<(), EncodeError> {
+        self.serialize_u64(v.to_bits())
+    }
+
+    fn serialize_char(self, v: char) -> Result<(), EncodeError> {
+        self.serialize_u32(v as u32)
+    }
+
+    fn serialize_str(self, v: &str) -> Result<(), EncodeError> {
+        self.serialize_bytes(v.as_bytes())
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<(), EncodeError> {
+        self.len(v.len());
+        self.out.extend_from_slice(v);
+        Ok(())
+    }
+
+    fn serialize_none(self) -> Result<(), EncodeError> {
+        self.out.push(0);
+        Ok(())
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<(), EncodeError> {
+        self.out.push(1);
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<(), EncodeError> {
+        Ok(())
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<(), EncodeError> {
+        Ok(())
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        index: u32,
+        _variant: &'static str,
+    ) -> Result<(), EncodeError> {
+        self.variant(index);
+        Ok(())
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<(), EncodeError> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        index: u32,
+        _variant: &'static str,
+        value: &T,
+    ) -> Result<(), EncodeError> {
+        self.variant(index);
+        value.serialize(self)
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Collect<'a>, EncodeError> {
+        Ok(Collect::new(self, false))
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self, EncodeError> {
+        Ok(self)
+    }
+
+    fn serialize_tuple_struct(self, _name: &'static str, _len: usize) -> Result<Self, EncodeError> {
+        Ok(self)
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self, EncodeError> {
+        self.variant(index);
+        Ok(self)
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Collect<'a>, EncodeError> {
+        Ok(Collect::new(self, true))
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self, EncodeError> {
+        Ok(self)
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self, EncodeError> {
+        self.variant(index);
+        Ok(self)
+    }
+}
+
+impl<'a> ser::SerializeSeq for Collect<'a> {
+    type Ok = ();
+    type Error = EncodeError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), EncodeError> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<(), EncodeError> {
+        self.finish()
+    }
+}
+
+impl<'a> ser::SerializeMap for Collect<'a> {
+    type Ok = ();
+    type Error = EncodeError;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), EncodeError> {
+        self.pending_key = Some(canonical_bytes(key)?);
+        Ok(())
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), EncodeError> {
+        let mut entry = self
+            .pending_key
+            .take()
+            .ok_or_else(|| EncodeError("map value without key".to_string()))?;
+        entry.extend_from_slice(&canonical_bytes(value)?);
+        self.items.push(entry);
+        Ok(())
+    }
+
+    fn end(self) -> Result<(), EncodeError> {
+        self.finish()
+    }
+}
+
+macro_rules! fixed_compound {
+    ($($trait:ident::$method:ident),*) => {
+        $(impl<'a> ser::$trait for &'a mut Encoder {
+            type Ok = ();
+            type Error = EncodeError;
+
+            fn $method<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), EncodeError> {
+                value.serialize(&mut **self)
+            }
+
+            fn end(self) -> Result<(), EncodeError> {
+                Ok(())
+            }
+        })*
+    };
+}
+
+fixed_compound!(
+    SerializeTuple::serialize_element,
+    SerializeTupleStruct::serialize_field,
+    SerializeTupleVariant::serialize_field
+);
+
+macro_rules! struct_compound {
+    ($($trait:ident),*) => {
+        $(impl<'a> ser::$trait for &'a mut Encoder {
+            type Ok = ();
+            type Error = EncodeError;
+
+            fn serialize_field<T: Serialize + ?Sized>(
+                &mut self,
+                _key: &'static str,
+                value: &T,
+            ) -> Result<(), EncodeError> {
+                value.serialize(&mut **self)
+            }
+
+            fn end(self) -> Result<(), EncodeError> {
+                Ok(())
+            }
+        })*
+    };
+}
+
+struct_compound!(SerializeStruct, SerializeStructVariant);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state_hash;
+    use std::collections::{BTreeMap, HashMap};
+
+    #[test]
+    fn test_integers_are_little_endian() {
+        assert_eq!(canonical_bytes(&0x0102_0304u32).unwrap(), [4, 3, 2, 1]);
+        assert_eq!(canonical_bytes(&-1i16).unwrap(), [0xFF, 0xFF]);
+        assert_eq!(
+            canonical_bytes("ab").unwrap(),
+            [2, 0, 0, 0, 0, 0, 0, 0, b'a', b'b']
+        );
+    }
+
+    #[test]
+    fn test_map_encoding_ignores_iteration_order() {
+        let mut hashed = HashMap::new();
+        let mut sorted = BTreeMap::new();
+        for i in 0..64u32 {
+            hashed.insert(i.wrapping_mul(2_654_435_761), i);
+            sorted.insert(i.wrapping_mul(2_654_435_761), i);
+        }
+        assert_eq!(
+            canonical_bytes(&hashed).unwrap(),
+            canonical_bytes(&sorted).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_option_and_sequence_are_unambiguous() {
+        let a: (Vec<u8>, Vec<u8>) = (vec![1], vec![]);
+        let b: (Vec<u8>, Vec<u8>) = (vec![], vec![1]);
+        assert_ne!(hash_value(&a), hash_value(&b));
+        assert_ne!(hash_value(&Some(0u8)), hash_value(&Option::<u8>::None));
+    }
+
+    #[test]
+    fn test_globals_cover_every_state_field() {
+        // A new State field must be folded into a digest component; update `Globals`
+        // (or add a component) and this list together.
+        let value = serde_json::to_value(State::new()).unwrap();
+        let fields: Vec<&str> = value
+            .as_object()
+            .unwrap()
+            .keys()
+            .map(String::as_str)
+            .collect();
+        let mut expected = vec![
+            "turn",
+            "rules_ver",
+            "map",
+            "units",
+            "next_unit_id",
+            "cities",
+            "next_city_id",
+            "rng",
+            "players",
+        ];
+        expected.sort();
+        assert_eq!(fields, expected);
+    }
+
+    #[test]
+    fn test_incremental_matches_full_recompute() {
+        use crate::{apply_action, Action, Map, TileCoord};
+        let mut state = State::with_seed(11);
+        state.map = Map::new(20, 20).unwrap();
+        state.add_player(PlayerId(0), "Natufians");
+        let city = state
+            .found_city(PlayerId(0), "Ain Mallaha", TileCoord::new(3, 3))
+            .unwrap();
+        state
+            .spawn_unit(PlayerId(0), "warrior", TileCoord::new(4, 4))
+            .unwrap();
+        let mut hasher = IncrementalHasher::new(&state).with_verification(true);
+        assert_eq!(hasher.hash(), state_hash(&state));
+
+        let build = Action::BuildUnit {
+            city,
+            kind: "warrior".to_string(),
+        };
+        let effects = apply_action(&mut state, PlayerId(0), build).unwrap();
+        assert_eq!(hasher.update(&state, &effects).unwrap(), state_hash(&state));
+
+        let effects = apply_action(&mut state, PlayerId(0), Action::EndTurn).unwrap();
+        assert_eq!(hasher.update(&state, &effects).unwrap(), state_hash(&state));
+    }
+
+    #[test]
+    fn test_verification_reports_untracked_mutation() {
+        let mut state = State::new();
+        state.map = crate::Map::new(4, 4).unwrap();
+        let unit = state
+            .spawn_unit(PlayerId(0), "warrior", crate::TileCoord::new(1, 1))
+            .unwrap();
+        let mut hasher = IncrementalHasher::new(&state).with_verification(true);
+        state.units.get_mut(&unit).unwrap().hp = 1;
+        let effects = Effects::default();
+        // The unit changed without being reported as touched
+        let err = hasher.update(&state, &effects).unwrap_err();
+        assert!(err.to_string().contains("units"));
+    }
+
+    #[test]
+    fn test_distinct_states_do_not_collide() {
+        let a = State::new();
+        let mut b = State::new();
+        b.rng = crate::Rng::new(1);
+        assert_eq!(a.turn, b.turn);
+        assert_ne!(state_hash(&a), state_hash(&b));
+    }
+}
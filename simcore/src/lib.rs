@@ -1,23 +1,62 @@
 //! SimCore - Deterministic strategy simulation core
 
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::BTreeMap;
 use thiserror::Error;
 
+pub mod city;
+pub mod combat;
+pub mod district;
+pub mod divergence;
+pub mod effects;
+pub mod fixed;
+pub mod fog;
+pub mod hash;
+pub mod map;
+pub mod movement;
+pub mod pathfind;
+pub mod player;
+pub mod policy;
+pub mod rng;
+pub mod rules;
+pub mod siege;
+pub mod supply;
+pub mod tech;
+mod turn;
+pub mod unit;
+pub mod validation;
+pub mod yields;
+pub mod zoc;
+
+pub use city::{City, District, ProductionItem, ProductionOrder};
+pub use combat::{preview_combat, CombatPreview};
+pub use divergence::{locate_divergence, DivergenceReport, HashTree};
+use effects::Recorder;
+pub use effects::{apply_effects, revert_effects, Delta, Effects, Event, Touched};
+pub use fixed::Fixed;
+pub use hash::{IncrementalHasher, StateDigest};
+pub use map::{HexDir, Map, MapSize, Resource, Terrain, Tile};
+pub use pathfind::{Pathfinder, Route};
+pub use player::Player;
+pub use rng::{Rng, RngStream};
+pub use rules::{Rules, RulesError};
+pub use unit::{Unit, UnitClass};
+pub use validation::ValidationError;
+
 /// Opaque player identifier
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 pub struct PlayerId(pub u64);
 
 /// Opaque city identifier
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 pub struct CityId(pub u64);
 
 /// Opaque unit identifier
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 pub struct UnitId(pub u64);
 
 /// Tile coordinate
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 pub struct TileCoord {
     pub x: i32,
     pub y: i32,
@@ -27,6 +66,12 @@ pub struct TileCoord {
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Hash128(pub u128);
 
+impl std::fmt::Display for Hash128 {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:032x}", self.0)
+    }
+}
+
 /// Game action enum per SimCore Contract
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum Action {
@@ -40,6 +85,16 @@ pub enum Action {
         attacker: UnitId,
         target: UnitId,
     },
+    /// Bombard or assault a city's walls
+    AttackCity {
+        attacker: UnitId,
+        city: CityId,
+    },
+    /// A city's ranged strike on a unit
+    CityStrike {
+        city: CityId,
+        target: UnitId,
+    },
     Fortify {
         unit: UnitId,
     },
@@ -70,36 +125,181 @@ pub enum Action {
     },
 }
 
+impl Action {
+    /// Variant name, e.g. for `ActionLite.action_type`
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Action::EndTurn => "EndTurn",
+            Action::MoveUnit { .. } => "MoveUnit",
+            Action::Attack { .. } => "Attack",
+            Action::AttackCity { .. } => "AttackCity",
+            Action::CityStrike { .. } => "CityStrike",
+            Action::Fortify { .. } => "Fortify",
+            Action::BuildUnit { .. } => "BuildUnit",
+            Action::BuildDistrict { .. } => "BuildDistrict",
+            Action::SetPolicy { .. } => "SetPolicy",
+            Action::ChooseTech { .. } => "ChooseTech",
+            Action::OfferDeal { .. } => "OfferDeal",
+            Action::AcceptDeal { .. } => "AcceptDeal",
+            Action::DeclineDeal { .. } => "DeclineDeal",
+        }
+    }
+}
+
 /// Simulation error
 #[derive(Error, Debug)]
 pub enum SimError {
     #[error("Invalid action: {0}")]
-    InvalidAction(String),
+    Validation(#[from] ValidationError),
     #[error("Invariant violation: {0}")]
     InvariantViolation(String),
+    #[error("Invalid map: {0}")]
+    InvalidMap(String),
+    #[error(transparent)]
+    Rules(#[from] RulesError),
+}
+
+impl SimError {
+    /// Stable rejection code when the error is a validation failure
+    pub fn code(&self) -> Option<u32> {
+        match self {
+            SimError::Validation(e) => Some(e.code()),
+            _ => None,
+        }
+    }
 }
 
-/// Game state (placeholder)
+/// Full simulation state: map, players, units, cities, RNG and the pinned rules version
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct State {
     pub turn: i32,
     pub rules_ver: String,
-    // Placeholder fields
-    _players: HashMap<PlayerId, ()>,
-    _cities: HashMap<CityId, ()>,
-    _units: HashMap<UnitId, ()>,
+    pub map: Map,
+    pub units: BTreeMap<UnitId, Unit>,
+    next_unit_id: u64,
+    pub cities: BTreeMap<CityId, City>,
+    next_city_id: u64,
+    pub rng: Rng,
+    pub players: BTreeMap<PlayerId, Player>,
 }
 
 impl State {
     pub fn new() -> Self {
         Self {
             turn: 0,
-            rules_ver: "0.1.0".to_string(),
-            _players: HashMap::new(),
-            _cities: HashMap::new(),
-            _units: HashMap::new(),
+            rules_ver: rules::DEFAULT_VERSION.to_string(),
+            map: Map::default(),
+            units: BTreeMap::new(),
+            next_unit_id: 0,
+            cities: BTreeMap::new(),
+            next_city_id: 0,
+            rng: Rng::default(),
+            players: BTreeMap::new(),
+        }
+    }
+
+    /// Empty state whose RNG streams are seeded with `seed`
+    pub fn with_seed(seed: u64) -> Self {
+        Self {
+            rng: Rng::new(seed),
+            ..Self::new()
         }
     }
+
+    /// Empty state pinned to a specific ruleset version
+    pub fn with_rules(version: &str) -> Result<Self, SimError> {
+        Rules::load(version)?;
+        Ok(Self {
+            rules_ver: version.to_string(),
+            ..Self::new()
+        })
+    }
+
+    /// The ruleset this state was created with
+    pub fn rules(&self) -> Result<&'static Rules, SimError> {
+        Ok(Rules::load(&self.rules_ver)?)
+    }
+
+    pub fn player(&self, id: PlayerId) -> Option<&Player> {
+        self.players.get(&id)
+    }
+
+    /// Register a player under `id`, replacing any previous entry
+    pub fn add_player(&mut self, id: PlayerId, name: &str) {
+        self.players.insert(id, Player::new(id, name));
+    }
+
+    pub fn unit(&self, id: UnitId) -> Option<&Unit> {
+        self.units.get(&id)
+    }
+
+    /// Units standing on `coord`, in id order
+    pub fn units_at(&self, coord: TileCoord) -> impl Iterator<Item = &Unit> {
+        self.units.values().filter(move |u| u.pos == coord)
+    }
+
+    /// The unit of `class` on `coord`, if any (1UPT allows at most one per class)
+    pub fn unit_of_class_at(&self, coord: TileCoord, class: UnitClass) -> Option<&Unit> {
+        self.units_at(coord).find(|u| u.class == class)
+    }
+
+    /// Place a new unit of a rules-defined kind, enforcing bounds, passability and 1UPT
+    pub fn spawn_unit(
+        &mut self,
+        owner: PlayerId,
+        kind: &str,
+        pos: TileCoord,
+    ) -> Result<UnitId, SimError> {
+        let def = self
+            .rules()?
+            .unit(kind)
+            .ok_or_else(|| ValidationError::UnknownUnitKind(kind.to_string()))?;
+        self.check_enterable(pos)?;
+        if self.unit_of_class_at(pos, def.class).is_some() {
+            return Err(ValidationError::OneUnitPerTileClash(pos).into());
+        }
+        let id = UnitId(self.next_unit_id);
+        self.next_unit_id += 1;
+        self.units
+            .insert(id, Unit::new(id, owner, kind, def.class, pos, def.moves));
+        Ok(id)
+    }
+
+    /// In bounds and passable
+    pub fn check_enterable(&self, pos: TileCoord) -> Result<(), ValidationError> {
+        if !self.map.in_bounds(pos) {
+            return Err(ValidationError::OutOfBounds(pos));
+        }
+        if !self.map.is_passable(pos) {
+            return Err(ValidationError::Impassable(pos));
+        }
+        Ok(())
+    }
+
+    pub fn city(&self, id: CityId) -> Option<&City> {
+        self.cities.get(&id)
+    }
+
+    pub fn city_at(&self, coord: TileCoord) -> Option<&City> {
+        self.cities.values().find(|c| c.pos == coord)
+    }
+
+    /// Found a new city on a passable tile not already holding a city
+    pub fn found_city(
+        &mut self,
+        owner: PlayerId,
+        name: &str,
+        pos: TileCoord,
+    ) -> Result<CityId, SimError> {
+        self.check_enterable(pos)?;
+        if self.city_at(pos).is_some() {
+            return Err(ValidationError::CityTileUnavailable(pos).into());
+        }
+        let id = CityId(self.next_city_id);
+        self.next_city_id += 1;
+        self.cities.insert(id, City::new(id, owner, name, pos));
+        Ok(id)
+    }
 }
 
 impl Default for State {
@@ -108,52 +308,216 @@ impl Default for State {
     }
 }
 
-/// Effects from applying an action
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct Effects {
-    pub deltas: Vec<String>,
-    pub events: Vec<String>,
-}
-
 /// Enumerate all legal actions for a player
-pub fn enumerate_legal_actions(_state: &State, _player: PlayerId) -> Vec<Action> {
-    // Placeholder: returns empty vector
-    Vec::new()
+pub fn enumerate_legal_actions(state: &State, player: PlayerId) -> Vec<Action> {
+    let mut actions = vec![Action::EndTurn];
+    if let (Ok(rules), Some(p)) = (state.rules(), state.player(player)) {
+        let techs = tech::available(rules, p);
+        actions.extend(techs.into_iter().map(|id| Action::ChooseTech { id }));
+        for slot in policy::open_slots(rules, p) {
+            for id in rules.policies.keys() {
+                let action = Action::SetPolicy {
+                    slot,
+                    id: id.clone(),
+                };
+                if validate_action(state, player, &action).is_ok() {
+                    actions.push(action);
+                }
+            }
+        }
+    }
+    for city in state.cities.values().filter(|c| c.owner == player) {
+        for spot in district::placements(state, city.id).unwrap_or_default() {
+            let action = Action::BuildDistrict {
+                city: city.id,
+                kind: spot.kind,
+                tile: spot.tile,
+            };
+            if validate_action(state, player, &action).is_ok() {
+                actions.push(action);
+            }
+        }
+    }
+    let Ok(paths) = Pathfinder::new(state, player) else {
+        return actions;
+    };
+    for unit in state.units.values().filter(|u| u.owner == player) {
+        let fortify = Action::Fortify { unit: unit.id };
+        if validate_action(state, player, &fortify).is_ok() {
+            actions.push(fortify);
+        }
+        for route in paths.reachable(unit.id).into_values() {
+            let action = route.into_action(unit.id);
+            // Fog can hide blockers the pathfinder did not know about
+            if validate_action(state, player, &action).is_ok() {
+                actions.push(action);
+            }
+        }
+    }
+    actions
 }
 
-/// Validate an action against current state
-pub fn validate_action(_state: &State, _action: &Action) -> Result<(), SimError> {
-    // Placeholder: always succeeds
-    Ok(())
+/// Validate `player`'s action against current state
+///
+/// The actor must be on the roster, and may only order its own units and cities.
+pub fn validate_action(state: &State, player: PlayerId, action: &Action) -> Result<(), SimError> {
+    if state.player(player).is_none() {
+        return Err(ValidationError::PlayerNotFound(player).into());
+    }
+    match action {
+        Action::MoveUnit { unit, path, ap } => {
+            movement::validate_move(state, player, *unit, path, *ap)
+        }
+        Action::Attack { attacker, target } => {
+            combat::validate_attack(state, player, *attacker, *target)
+        }
+        Action::AttackCity { attacker, city } => {
+            siege::validate_attack_city(state, player, *attacker, *city)
+        }
+        Action::Fortify { unit } => unit::validate_fortify(state, player, *unit),
+        Action::ChooseTech { id } => tech::validate_choose(state, player, id),
+        Action::SetPolicy { slot, id } => policy::validate_set(state, player, *slot, id),
+        Action::CityStrike { city, target } => {
+            siege::validate_strike(state, player, *city, *target)
+        }
+        Action::BuildUnit { city, kind } => city::validate_build_unit(state, player, *city, kind),
+        Action::BuildDistrict { city, kind, tile } => {
+            city::validate_build_district(state, player, *city, kind, *tile)
+        }
+        // Placeholder: remaining actions always succeed
+        _ => Ok(()),
+    }
 }
 
-/// Apply an action to state, returning effects
-pub fn apply_action(_state: &mut State, _action: Action) -> Result<Effects, SimError> {
-    // Placeholder: no-op
-    Ok(Effects {
-        deltas: Vec::new(),
-        events: Vec::new(),
-    })
+/// Apply `player`'s action to state, returning effects
+pub fn apply_action(
+    state: &mut State,
+    player: PlayerId,
+    action: Action,
+) -> Result<Effects, SimError> {
+    validate_action(state, player, &action)?;
+    let mut rec = Recorder::begin(state);
+    match action {
+        Action::EndTurn => return end_turn_with_effects(state),
+        Action::MoveUnit { unit, path, ap } => {
+            movement::apply_move(state, unit, &path, ap, &mut rec)?;
+        }
+        Action::Attack { attacker, target } => {
+            combat::apply_attack(state, attacker, target, &mut rec)?;
+        }
+        Action::AttackCity { attacker, city } => {
+            siege::apply_attack_city(state, attacker, city, &mut rec)?;
+        }
+        Action::CityStrike { city, target } => {
+            siege::apply_strike(state, city, target, &mut rec)?;
+        }
+        Action::Fortify { unit } => unit::apply_fortify(state, unit, &mut rec),
+        Action::ChooseTech { id } => tech::apply_choose(state, player, id, &mut rec),
+        Action::SetPolicy { slot, id } => policy::apply_set(state, player, slot, id, &mut rec)?,
+        Action::BuildUnit { city, kind } => {
+            rec.city(state, city);
+            city::enqueue(state, city, ProductionItem::Unit { kind }, &mut rec)?;
+        }
+        Action::BuildDistrict { city, kind, tile } => {
+            rec.city(state, city);
+            city::enqueue(
+                state,
+                city,
+                ProductionItem::District { kind, tile },
+                &mut rec,
+            )?;
+        }
+        // Placeholder: remaining actions are no-ops
+        _ => {}
+    }
+    Ok(rec.finish(state))
 }
 
 /// Execute end-of-turn processing
 pub fn end_turn(state: &mut State) -> Result<(), SimError> {
-    // Placeholder: increment turn
-    state.turn += 1;
+    end_turn_with_effects(state).map(|_| ())
+}
+
+/// Execute end-of-turn processing, returning what it changed
+pub fn end_turn_with_effects(state: &mut State) -> Result<Effects, SimError> {
+    let mut rec = Recorder::begin_all(state);
+    turn::run(state, &mut rec)?;
+    Ok(rec.finish(state))
+}
+
+/// Freeze `tech` for `player`, for `turns` turns counting this one or until lifted
+pub fn freeze_tech(
+    state: &mut State,
+    player: PlayerId,
+    tech: &str,
+    cause: tech::FreezeCause,
+    turns: Option<u32>,
+) -> Result<Effects, SimError> {
+    check_tech_target(state, player, tech)?;
+    let until = turns.map(|n| state.turn + n.max(1) as i32 - 1);
+    let mut rec = Recorder::begin(state);
+    tech::freeze(state, player, tech, tech::Freeze { cause, until }, &mut rec);
+    Ok(rec.finish(state))
+}
+
+/// Lift any freeze on `tech` for `player`
+pub fn unfreeze_tech(state: &mut State, player: PlayerId, tech: &str) -> Result<Effects, SimError> {
+    check_tech_target(state, player, tech)?;
+    let mut rec = Recorder::begin(state);
+    tech::unfreeze(state, player, tech, &mut rec);
+    Ok(rec.finish(state))
+}
+
+fn check_tech_target(state: &State, player: PlayerId, tech: &str) -> Result<(), SimError> {
+    if state.player(player).is_none() {
+        return Err(ValidationError::PlayerNotFound(player).into());
+    }
+    if state.rules()?.tech(tech).is_none() {
+        return Err(ValidationError::TechNotAvailable(tech.to_string()).into());
+    }
     Ok(())
 }
 
-/// Compute deterministic state hash
+/// Compute deterministic state hash: xxh3-128 over per-component canonical sub-hashes
 pub fn state_hash(state: &State) -> Hash128 {
-    // Placeholder: deterministic hash based on turn number
-    // In production, this would hash all state fields in a stable order
-    let hash = state.turn as u128;
-    Hash128(hash)
+    hash::StateDigest::compute(state).root()
+}
+
+#[cfg(test)]
+pub(crate) mod test_util {
+    //! Setup and shorthand shared by the module tests
+
+    use crate::{validate_action, Action, Map, PlayerId, State, TileCoord, UnitId};
+
+    /// A fresh state on an all-grassland `width` x `height` map, with player 0 registered
+    pub fn new_state(width: i32, height: i32) -> State {
+        let mut state = State::new();
+        state.map = Map::new(width, height).unwrap();
+        state.add_player(PlayerId(0), "Natufians");
+        state
+    }
+
+    /// Rejection code for player 0 taking `action`; panics if the action is legal
+    pub fn code(state: &State, action: &Action) -> Option<u32> {
+        validate_action(state, PlayerId(0), action)
+            .unwrap_err()
+            .code()
+    }
+
+    /// Move `unit` along `path`, given as (x, y) pairs
+    pub fn mv(unit: UnitId, path: &[(i32, i32)], ap: i32) -> Action {
+        Action::MoveUnit {
+            unit,
+            path: path.iter().map(|&(x, y)| TileCoord::new(x, y)).collect(),
+            ap,
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use test_util::new_state;
 
     #[test]
     fn test_state_hash_deterministic() {
@@ -163,6 +527,125 @@ mod tests {
         assert_eq!(hash1, hash2);
     }
 
+    #[test]
+    fn test_spawn_unit_enforces_1upt() {
+        let mut state = State::new();
+        state.map = Map::new(4, 4).unwrap();
+        let pos = TileCoord::new(1, 1);
+        let owner = PlayerId(0);
+        let id = state.spawn_unit(owner, "warrior", pos).unwrap();
+        assert_eq!(state.unit(id).unwrap().pos, pos);
+        assert!(state.spawn_unit(owner, "warrior", pos).is_err());
+        assert!(state.spawn_unit(owner, "settler", pos).is_ok());
+        assert!(state
+            .spawn_unit(owner, "warrior", TileCoord::new(9, 9))
+            .is_err());
+        assert!(state
+            .spawn_unit(owner, "dragon", TileCoord::new(2, 2))
+            .is_err());
+        assert_eq!(state.units_at(pos).count(), 2);
+    }
+
+    #[test]
+    fn test_build_unit_completes_from_queue() {
+        let mut state = new_state(6, 6);
+        let pos = TileCoord::new(2, 2);
+        let city = state.found_city(PlayerId(0), "Jericho", pos).unwrap();
+        let build = Action::BuildUnit {
+            city,
+            kind: "warrior".to_string(),
+        };
+        apply_action(&mut state, PlayerId(0), build).unwrap();
+        assert_eq!(state.city(city).unwrap().queue.len(), 1);
+
+        for _ in 0..30 {
+            end_turn(&mut state).unwrap();
+        }
+        assert!(state.city(city).unwrap().queue.is_empty());
+        assert!(state.unit_of_class_at(pos, UnitClass::Combat).is_some());
+    }
+
+    #[test]
+    fn test_build_district_respects_slots() {
+        let mut state = new_state(6, 6);
+        let city = state
+            .found_city(PlayerId(0), "Jericho", TileCoord::new(2, 2))
+            .unwrap();
+        let district = |x| Action::BuildDistrict {
+            city,
+            kind: "campus".to_string(),
+            tile: TileCoord::new(x, 2),
+        };
+        let player = state.players.get_mut(&PlayerId(0)).unwrap();
+        player.known_techs.insert("writing".to_string());
+        apply_action(&mut state, PlayerId(0), district(3)).unwrap();
+        assert_eq!(state.city(city).unwrap().queue[0].cost, 60);
+        assert!(validate_action(&state, PlayerId(0), &district(3)).is_err());
+        assert!(validate_action(&state, PlayerId(0), &district(1)).is_err());
+        assert!(validate_action(&state, PlayerId(0), &district(99)).is_err());
+    }
+
+    #[test]
+    fn test_state_pinned_to_rules_version() {
+        let state = State::with_rules(rules::DEFAULT_VERSION).unwrap();
+        assert_eq!(state.rules().unwrap().version, state.rules_ver);
+        assert!(State::with_rules("0.0.0-missing").is_err());
+
+        let mut state = new_state(6, 6);
+        let city = state
+            .found_city(PlayerId(0), "Jericho", TileCoord::new(2, 2))
+            .unwrap();
+        let player = state.players.get_mut(&PlayerId(0)).unwrap();
+        player.known_techs.insert("archery".to_string());
+        let build = |kind: &str| Action::BuildUnit {
+            city,
+            kind: kind.to_string(),
+        };
+        assert!(validate_action(&state, PlayerId(0), &build("archer")).is_ok());
+        assert!(validate_action(&state, PlayerId(0), &build("trebuchet")).is_err());
+    }
+
+    #[test]
+    fn test_rejections_carry_codes() {
+        let mut state = new_state(6, 6);
+        state.add_player(PlayerId(1), "Khiamians");
+        let city = state
+            .found_city(PlayerId(0), "Jericho", TileCoord::new(2, 2))
+            .unwrap();
+        let code = |r: Result<(), SimError>| r.unwrap_err().code();
+
+        let build = Action::BuildUnit {
+            city,
+            kind: "warrior".to_string(),
+        };
+        assert_eq!(code(validate_action(&state, PlayerId(1), &build)), Some(4));
+        assert_eq!(code(validate_action(&state, PlayerId(7), &build)), Some(3));
+        let end = validate_action(&state, PlayerId(7), &Action::EndTurn);
+        assert_eq!(code(end), Some(3));
+        assert!(validate_action(&state, PlayerId(0), &build).is_ok());
+
+        let missing = Action::BuildUnit {
+            city: CityId(99),
+            kind: "warrior".to_string(),
+        };
+        assert_eq!(
+            code(validate_action(&state, PlayerId(0), &missing)),
+            Some(2)
+        );
+        let off_map = state.spawn_unit(PlayerId(0), "warrior", TileCoord::new(9, 9));
+        assert_eq!(off_map.unwrap_err().code(), Some(5));
+        state
+            .spawn_unit(PlayerId(0), "warrior", TileCoord::new(1, 1))
+            .unwrap();
+        let clash = state.spawn_unit(PlayerId(1), "archer", TileCoord::new(1, 1));
+        assert!(matches!(
+            clash,
+            Err(SimError::Validation(ValidationError::OneUnitPerTileClash(
+                _
+            )))
+        ));
+    }
+
     #[test]
     fn test_end_turn_increments() {
         let mut state = State::new();
@@ -177,18 +660,39 @@ mod tests {
         use super::*;
         use proptest::prelude::*;
 
-        // Arbitrary generator for TileCoord
+        // Arbitrary generator for TileCoord, mostly on or just off the seeded map
         impl Arbitrary for TileCoord {
             type Parameters = ();
             type Strategy = BoxedStrategy<Self>;
 
             fn arbitrary_with(_: Self::Parameters) -> Self::Strategy {
-                (any::<i32>(), any::<i32>())
-                    .prop_map(|(x, y)| TileCoord { x, y })
-                    .boxed()
+                prop_oneof![
+                    3 => (-1..9, -1..7),
+                    1 => (any::<i32>(), any::<i32>()),
+                ]
+                .prop_map(|(x, y)| TileCoord { x, y })
+                .boxed()
             }
         }
 
+        /// Ids that mostly name entities of the seeded state
+        fn entity_id() -> impl Strategy<Value = u64> {
+            prop_oneof![3 => 0..8u64, 1 => any::<u64>()]
+        }
+
+        /// Rules-defined names mixed with junk
+        fn name(known: &'static [&'static str]) -> impl Strategy<Value = String> {
+            prop_oneof![
+                3 => prop::sample::select(known).prop_map(String::from),
+                1 => "[a-z]{3,8}",
+            ]
+        }
+
+        const UNIT_KINDS: &[&str] = &["warrior", "slinger", "settler", "builder"];
+        const DISTRICT_KINDS: &[&str] = &["campus", "encampment", "holy_site"];
+        const POLICIES: &[&str] = &["discipline", "god_king", "agoge"];
+        const TECHS: &[&str] = &["foraging", "pottery", "writing", "archery"];
+
         // Arbitrary generator for Action
         impl Arbitrary for Action {
             type Parameters = ();
@@ -199,28 +703,40 @@ mod tests {
                     // EndTurn
                     Just(Action::EndTurn),
                     // MoveUnit
-                    (any::<u64>(), prop::collection::vec(any::<TileCoord>(), 0..10), any::<i32>())
+                    (
+                        entity_id(),
+                        prop::collection::vec(any::<TileCoord>(), 0..4),
+                        prop_oneof![0..6, any::<i32>()]
+                    )
                         .prop_map(|(unit, path, ap)| Action::MoveUnit {
                             unit: UnitId(unit),
                             path,
                             ap,
                         }),
                     // Attack
-                    (any::<u64>(), any::<u64>()).prop_map(|(attacker, target)| Action::Attack {
+                    (entity_id(), entity_id()).prop_map(|(attacker, target)| Action::Attack {
                         attacker: UnitId(attacker),
                         target: UnitId(target),
                     }),
-                    // Fortify
-                    any::<u64>().prop_map(|unit| Action::Fortify {
-                        unit: UnitId(unit),
+                    // AttackCity
+                    (entity_id(), entity_id()).prop_map(|(attacker, city)| Action::AttackCity {
+                        attacker: UnitId(attacker),
+                        city: CityId(city),
                     }),
+                    // CityStrike
+                    (entity_id(), entity_id()).prop_map(|(city, target)| Action::CityStrike {
+                        city: CityId(city),
+                        target: UnitId(target),
+                    }),
+                    // Fortify
+                    entity_id().prop_map(|unit| Action::Fortify { unit: UnitId(unit) }),
                     // BuildUnit
-                    (any::<u64>(), "[a-z]{3,8}").prop_map(|(city, kind)| Action::BuildUnit {
+                    (entity_id(), name(UNIT_KINDS)).prop_map(|(city, kind)| Action::BuildUnit {
                         city: CityId(city),
                         kind,
                     }),
                     // BuildDistrict
-                    (any::<u64>(), "[a-z]{3,8}", any::<TileCoord>()).prop_map(
+                    (entity_id(), name(DISTRICT_KINDS), any::<TileCoord>()).prop_map(
                         |(city, kind, tile)| Action::BuildDistrict {
                             city: CityId(city),
                             kind,
@@ -228,12 +744,10 @@ mod tests {
                         }
                     ),
                     // SetPolicy
-                    (any::<i32>(), "[a-z]{3,8}").prop_map(|(slot, id)| Action::SetPolicy {
-                        slot,
-                        id,
-                    }),
+                    (prop_oneof![0..3, any::<i32>()], name(POLICIES))
+                        .prop_map(|(slot, id)| Action::SetPolicy { slot, id }),
                     // ChooseTech
-                    "[a-z]{3,8}".prop_map(|id| Action::ChooseTech { id }),
+                    name(TECHS).prop_map(|id| Action::ChooseTech { id }),
                     // OfferDeal
                     "\\{.*\\}".prop_map(|json| Action::OfferDeal { json }),
                     // AcceptDeal
@@ -245,29 +759,94 @@ mod tests {
             }
         }
 
+        /// Two players on a small map, each with a city, a combat unit and a civilian; the
+        /// warriors stand side by side, within reach of both cities
+        fn seeded_state(seed: u64) -> State {
+            let mut state = State::with_seed(seed);
+            state.map = Map::new(8, 6).unwrap();
+            state
+                .map
+                .set_terrain(TileCoord::new(4, 1), Terrain::Mountain)
+                .unwrap();
+            for (id, name, city, warrior, settler) in [
+                (0, "Natufians", (2, 2), (3, 2), (1, 3)),
+                (1, "Khiamians", (3, 3), (4, 2), (6, 3)),
+            ] {
+                let player = PlayerId(id);
+                let at = |(x, y)| TileCoord::new(x, y);
+                state.add_player(player, name);
+                state.found_city(player, name, at(city)).unwrap();
+                state.spawn_unit(player, "warrior", at(warrior)).unwrap();
+                state.spawn_unit(player, "settler", at(settler)).unwrap();
+            }
+            state
+        }
+
         /// Invariant check: 1UPT (one unit per tile) for combat units
-        fn check_1upt_invariant(_state: &State) -> Result<(), String> {
-            // TODO(spec): Implement 1UPT check
-            // - Collect all combat unit positions
-            // - Verify no two combat units occupy same tile
-            // - Allow civilian co-stack with 1 combat unit
+        fn check_1upt_invariant(state: &State) -> Result<(), String> {
+            let mut occupied = std::collections::BTreeSet::new();
+            for unit in state.units.values() {
+                let key = (unit.pos.x, unit.pos.y, unit.is_combat());
+                if !occupied.insert(key) {
+                    return Err(format!(
+                        "two {:?} units on ({}, {})",
+                        unit.class, unit.pos.x, unit.pos.y
+                    ));
+                }
+            }
             Ok(())
         }
 
         /// Invariant check: No units on out-of-bounds or impassable tiles
-        fn check_oob_invariant(_state: &State) -> Result<(), String> {
-            // TODO(spec): Implement OOB check
-            // - Verify all unit positions are within map bounds
-            // - Verify all unit positions are on passable tiles
+        fn check_oob_invariant(state: &State) -> Result<(), String> {
+            for unit in state.units.values() {
+                if !state.map.is_passable(unit.pos) {
+                    return Err(format!(
+                        "unit {:?} on OOB/impassable ({}, {})",
+                        unit.id, unit.pos.x, unit.pos.y
+                    ));
+                }
+            }
             Ok(())
         }
 
         /// Invariant check: Cost constraints honored
-        fn check_cost_invariant(_state: &State) -> Result<(), String> {
-            // TODO(spec): Implement cost constraint check
-            // - Verify no player has negative resources
-            // - Verify all built units/districts were paid for
-            // - Verify action points (AP) are non-negative
+        fn check_cost_invariant(state: &State) -> Result<(), String> {
+            for player in state.players.values() {
+                if [player.gold, player.culture, player.influence]
+                    .iter()
+                    .any(|v| *v < Fixed::ZERO)
+                {
+                    return Err(format!("player {:?} has negative resources", player.id));
+                }
+            }
+            let report = crate::yields::compute(state).map_err(|e| e.to_string())?;
+            for (kind, total) in report
+                .cities
+                .values()
+                .map(|c| c.yields.total)
+                .chain(report.players.values().map(|p| p.total))
+                .flat_map(|t| crate::rules::YieldKind::ALL.map(|k| (k, t.get(k))))
+            {
+                if total < Fixed::ZERO {
+                    return Err(format!("negative {:?} yield", kind));
+                }
+            }
+            for unit in state.units.values() {
+                if unit.moves_left < 0 {
+                    return Err(format!("unit {:?} has negative AP", unit.id));
+                }
+            }
+            for city in state.cities.values() {
+                if !(Fixed::ZERO..=city.food_cap()).contains(&city.food_stored)
+                    || !(Fixed::ZERO..=city.production_cap()).contains(&city.production_stored)
+                {
+                    return Err(format!("city {:?} storage outside caps", city.id));
+                }
+                if city.districts.len() > city.district_slots() {
+                    return Err(format!("city {:?} exceeds district slots", city.id));
+                }
+            }
             Ok(())
         }
 
@@ -281,31 +860,61 @@ mod tests {
 
         proptest! {
             /// Invariant fuzz test: random action streams never break constraints
-            /// 
+            ///
             /// Contract acceptance criterion: 10M random actions → 0 invariant breaks
             #[test]
-            fn invariant_fuzz_random_actions(actions in prop::collection::vec(any::<Action>(), 0..100)) {
-                let mut state = State::new();
-                
+            fn invariant_fuzz_random_actions(
+                actions in prop::collection::vec(any::<Action>(), 0..100)
+            ) {
+                let mut state = seeded_state(0);
+                check_all_invariants(&state).expect("Invariant violated in seeded state");
+
                 // Apply random action stream
                 for action in actions {
                     // Attempt to apply action (may fail if invalid, which is fine)
-                    let _ = apply_action(&mut state, action);
-                    
+                    let _ = apply_action(&mut state, PlayerId(0), action);
+
                     // After each action, invariants must hold
                     check_all_invariants(&state)
                         .expect("Invariant violated after applying action");
                 }
-                
+
                 // Final invariant check
                 check_all_invariants(&state)
                     .expect("Invariant violated at end of action stream");
             }
         }
 
+        proptest! {
+            /// Event sourcing: replaying recorded effects rebuilds the state, and reverting
+            /// them in reverse restores the start
+            #[test]
+            fn effects_replay_and_revert(
+                seed in any::<u64>(),
+                actions in prop::collection::vec(any::<Action>(), 0..30)
+            ) {
+                let start = seeded_state(seed);
+                let mut live = start.clone();
+                let log: Vec<Effects> = actions
+                    .into_iter()
+                    .filter_map(|a| apply_action(&mut live, PlayerId(0), a).ok())
+                    .collect();
+
+                let mut rebuilt = start.clone();
+                for effects in &log {
+                    apply_effects(&mut rebuilt, effects).unwrap();
+                }
+                prop_assert_eq!(state_hash(&rebuilt), state_hash(&live));
+                for effects in log.iter().rev() {
+                    revert_effects(&mut rebuilt, effects).unwrap();
+                }
+                prop_assert_eq!(state_hash(&rebuilt), state_hash(&start));
+            }
+        }
+
         proptest! {
             /// Determinism smoke test: fixed seed + action script produces same state_hash
-            /// 
+            ///
             /// Contract acceptance criterion: 100 random seeds → identical state_hash on replay
             #[test]
             fn determinism_smoke_fixed_seed(
@@ -313,23 +922,19 @@ mod tests {
                 actions in prop::collection::vec(any::<Action>(), 0..50)
             ) {
                 // First run: apply actions with given seed
-                let mut state1 = State::new();
-                // TODO(spec): Initialize state with seed
-                
+                let mut state1 = seeded_state(seed);
                 for action in actions.clone() {
-                    let _ = apply_action(&mut state1, action);
+                    let _ = apply_action(&mut state1, PlayerId(0), action);
                 }
                 let hash1 = state_hash(&state1);
-                
+
                 // Second run: replay with same seed and actions
-                let mut state2 = State::new();
-                // TODO(spec): Initialize state with same seed
-                
+                let mut state2 = seeded_state(seed);
                 for action in actions {
-                    let _ = apply_action(&mut state2, action);
+                    let _ = apply_action(&mut state2, PlayerId(0), action);
                 }
                 let hash2 = state_hash(&state2);
-                
+
                 // Hashes must be identical (determinism)
                 prop_assert_eq!(
                     hash1,
@@ -340,4 +945,3 @@ mod tests {
         }
     }
 }
-
@@ -0,0 +1,426 @@
+//! Hex map model
+//!
+//! Tiles are laid out as pointy-top hexes in "odd-r" offset coordinates: odd rows are
+//! shifted half a tile to the right. `TileCoord { x, y }` is column/row, with `y` growing
+//! downward. Distances are computed in cube space so they are exact hex step counts.
+
+use crate::{SimError, TileCoord};
+use serde::{Deserialize, Serialize};
+
+/// Largest width or height a map may have
+pub const MAX_DIMENSION: i32 = 128;
+
+/// Side length of the square tile blocks the map is hashed in
+pub const CHUNK_SIZE: i32 = 16;
+
+/// Preset map sizes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum MapSize {
+    Duel,
+    Small,
+    Standard,
+    Large,
+}
+
+impl MapSize {
+    /// (width, height) in tiles
+    pub fn dimensions(self) -> (i32, i32) {
+        match self {
+            MapSize::Duel => (44, 26),
+            MapSize::Small => (74, 46),
+            MapSize::Standard => (84, 54),
+            MapSize::Large => (96, 60),
+        }
+    }
+}
+
+/// Base terrain of a tile
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum Terrain {
+    Grassland,
+    Plains,
+    Desert,
+    Tundra,
+    Snow,
+    Forest,
+    Hills,
+    Marsh,
+    Mountain,
+    Coast,
+    Ocean,
+}
+
+impl Terrain {
+    /// Land units may enter this terrain
+    pub fn is_passable(self) -> bool {
+        !matches!(self, Terrain::Mountain | Terrain::Coast | Terrain::Ocean)
+    }
+
+    pub fn is_water(self) -> bool {
+        matches!(self, Terrain::Coast | Terrain::Ocean)
+    }
+
+    /// Elevation a freshly created tile of this terrain starts with
+    pub fn default_elevation(self) -> u8 {
+        match self {
+            Terrain::Hills => 1,
+            Terrain::Mountain => 2,
+            _ => 0,
+        }
+    }
+}
+
+/// Special resource on a tile
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum Resource {
+    Wheat,
+    Deer,
+    Cattle,
+    Fish,
+    Stone,
+    Flint,
+    Copper,
+    Iron,
+    Horses,
+}
+
+/// The six hex directions, in clockwise order starting east
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum HexDir {
+    East,
+    NorthEast,
+    NorthWest,
+    West,
+    SouthWest,
+    SouthEast,
+}
+
+impl HexDir {
+    pub const ALL: [HexDir; 6] = [
+        HexDir::East,
+        HexDir::NorthEast,
+        HexDir::NorthWest,
+        HexDir::West,
+        HexDir::SouthWest,
+        HexDir::SouthEast,
+    ];
+
+    pub fn opposite(self) -> HexDir {
+        match self {
+            HexDir::East => HexDir::West,
+            HexDir::NorthEast => HexDir::SouthWest,
+            HexDir::NorthWest => HexDir::SouthEast,
+            HexDir::West => HexDir::East,
+            HexDir::SouthWest => HexDir::NorthEast,
+            HexDir::SouthEast => HexDir::NorthWest,
+        }
+    }
+
+    fn bit(self) -> u8 {
+        1 << (self as u8)
+    }
+
+    /// (dx, dy) offset for this direction from a tile on row `y`
+    fn offset(self, y: i32) -> (i32, i32) {
+        let odd = y & 1 == 1;
+        match (self, odd) {
+            (HexDir::East, _) => (1, 0),
+            (HexDir::West, _) => (-1, 0),
+            (HexDir::NorthEast, false) => (0, -1),
+            (HexDir::NorthEast, true) => (1, -1),
+            (HexDir::NorthWest, false) => (-1, -1),
+            (HexDir::NorthWest, true) => (0, -1),
+            (HexDir::SouthWest, false) => (-1, 1),
+            (HexDir::SouthWest, true) => (0, 1),
+            (HexDir::SouthEast, false) => (0, 1),
+            (HexDir::SouthEast, true) => (1, 1),
+        }
+    }
+}
+
+impl TileCoord {
+    pub fn new(x: i32, y: i32) -> Self {
+        Self { x, y }
+    }
+
+    /// Adjacent coordinate in `dir` (may be out of bounds)
+    pub fn step(self, dir: HexDir) -> TileCoord {
+        let (dx, dy) = dir.offset(self.y);
+        TileCoord::new(self.x + dx, self.y + dy)
+    }
+
+    /// Hex step distance between two coordinates
+    pub fn distance(self, other: TileCoord) -> i32 {
+        let (aq, ar) = self.to_axial();
+        let (bq, br) = other.to_axial();
+        let dq = aq - bq;
+        let dr = ar - br;
+        (dq.abs() + dr.abs() + (dq + dr).abs()) / 2
+    }
+
+    /// Direction from `self` to an adjacent `other`, if they are neighbors
+    pub fn direction_to(self, other: TileCoord) -> Option<HexDir> {
+        HexDir::ALL.into_iter().find(|&d| self.step(d) == other)
+    }
+
+    pub fn is_adjacent(self, other: TileCoord) -> bool {
+        self.direction_to(other).is_some()
+    }
+
+    fn to_axial(self) -> (i32, i32) {
+        let q = self.x - (self.y - (self.y & 1)) / 2;
+        (q, self.y)
+    }
+}
+
+/// A single map tile
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Tile {
+    pub terrain: Terrain,
+    pub elevation: u8,
+    pub resource: Option<Resource>,
+    /// Bitmask of `HexDir` edges that carry a river
+    rivers: u8,
+}
+
+impl Tile {
+    pub fn new(terrain: Terrain) -> Self {
+        Self {
+            terrain,
+            elevation: terrain.default_elevation(),
+            resource: None,
+            rivers: 0,
+        }
+    }
+
+    pub fn is_passable(&self) -> bool {
+        self.terrain.is_passable()
+    }
+
+    pub fn has_river(&self, dir: HexDir) -> bool {
+        self.rivers & dir.bit() != 0
+    }
+
+    /// Any edge of this tile carries a river
+    pub fn is_riverside(&self) -> bool {
+        self.rivers != 0
+    }
+}
+
+/// Bounded hex map
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Map {
+    width: i32,
+    height: i32,
+    /// Row-major tiles, `width * height` long
+    tiles: Vec<Tile>,
+}
+
+impl Map {
+    /// Create a map filled with grassland
+    pub fn new(width: i32, height: i32) -> Result<Self, SimError> {
+        if !(0..=MAX_DIMENSION).contains(&width) || !(0..=MAX_DIMENSION).contains(&height) {
+            return Err(SimError::InvalidMap(format!(
+                "dimensions {}x{} outside 0..={}",
+                width, height, MAX_DIMENSION
+            )));
+        }
+        Ok(Self {
+            width,
+            height,
+            tiles: vec![Tile::new(Terrain::Grassland); (width * height) as usize],
+        })
+    }
+
+    pub fn with_size(size: MapSize) -> Self {
+        let (width, height) = size.dimensions();
+        Self::new(width, height).expect("preset map sizes are within bounds")
+    }
+
+    pub fn width(&self) -> i32 {
+        self.width
+    }
+
+    pub fn height(&self) -> i32 {
+        self.height
+    }
+
+    pub fn in_bounds(&self, coord: TileCoord) -> bool {
+        (0..self.width).contains(&coord.x) && (0..self.height).contains(&coord.y)
+    }
+
+    fn index(&self, coord: TileCoord) -> Option<usize> {
+        self.in_bounds(coord)
+            .then(|| (coord.y * self.width + coord.x) as usize)
+    }
+
+    pub fn tile(&self, coord: TileCoord) -> Option<&Tile> {
+        self.index(coord).map(|i| &self.tiles[i])
+    }
+
+    pub fn tile_mut(&mut self, coord: TileCoord) -> Option<&mut Tile> {
+        self.index(coord).map(move |i| &mut self.tiles[i])
+    }
+
+    /// In bounds and enterable by land units
+    pub fn is_passable(&self, coord: TileCoord) -> bool {
+        self.tile(coord).is_some_and(Tile::is_passable)
+    }
+
+    /// Set terrain and reset elevation to the terrain default
+    pub fn set_terrain(&mut self, coord: TileCoord, terrain: Terrain) -> Result<(), SimError> {
+        let tile = self.tile_mut(coord).ok_or_else(|| oob(coord))?;
+        tile.terrain = terrain;
+        tile.elevation = terrain.default_elevation();
+        Ok(())
+    }
+
+    /// Mark the edge between `coord` and its neighbor in `dir` as a river. Both tiles
+    /// record the edge so lookups work from either side.
+    pub fn set_river(&mut self, coord: TileCoord, dir: HexDir) -> Result<(), SimError> {
+        let other = coord.step(dir);
+        if !self.in_bounds(other) {
+            return Err(oob(other));
+        }
+        self.tile_mut(coord).ok_or_else(|| oob(coord))?.rivers |= dir.bit();
+        self.tile_mut(other).ok_or_else(|| oob(other))?.rivers |= dir.opposite().bit();
+        Ok(())
+    }
+
+    /// A river runs along the shared edge of two adjacent tiles
+    pub fn river_between(&self, a: TileCoord, b: TileCoord) -> bool {
+        match (a.direction_to(b), self.tile(a)) {
+            (Some(dir), Some(tile)) => tile.has_river(dir),
+            _ => false,
+        }
+    }
+
+    /// In-bounds neighbors of `coord`, in `HexDir::ALL` order
+    pub fn neighbors(&self, coord: TileCoord) -> impl Iterator<Item = TileCoord> + '_ {
+        HexDir::ALL
+            .into_iter()
+            .map(move |d| coord.step(d))
+            .filter(move |c| self.in_bounds(*c))
+    }
+
+    /// In-bounds tiles within `radius` steps of `center`, in row-major order
+    pub fn tiles_within(&self, center: TileCoord, radius: i32) -> Vec<TileCoord> {
+        let mut out = Vec::new();
+        for y in (center.y - radius).max(0)..=(center.y + radius).min(self.height - 1) {
+            for x in (center.x - radius - 1).max(0)..=(center.x + radius + 1).min(self.width - 1) {
+                let c = TileCoord::new(x, y);
+                if c.distance(center) <= radius {
+                    out.push(c);
+                }
+            }
+        }
+        out
+    }
+
+    /// All coordinates in row-major order
+    pub fn coords(&self) -> impl Iterator<Item = TileCoord> + '_ {
+        (0..self.height).flat_map(move |y| (0..self.width).map(move |x| TileCoord::new(x, y)))
+    }
+
+    fn chunks_x(&self) -> i32 {
+        (self.width + CHUNK_SIZE - 1) / CHUNK_SIZE
+    }
+
+    /// Number of `CHUNK_SIZE` x `CHUNK_SIZE` blocks covering the map
+    pub fn chunk_count(&self) -> usize {
+        (self.chunks_x() * ((self.height + CHUNK_SIZE - 1) / CHUNK_SIZE)) as usize
+    }
+
+    /// Index of the chunk containing an in-bounds `coord`
+    pub fn chunk_of(&self, coord: TileCoord) -> usize {
+        ((coord.y / CHUNK_SIZE) * self.chunks_x() + coord.x / CHUNK_SIZE) as usize
+    }
+
+    /// Tiles of chunk `index`, in row-major order
+    pub fn chunk_tiles(&self, index: usize) -> Vec<&Tile> {
+        let cx = (index as i32 % self.chunks_x()) * CHUNK_SIZE;
+        let cy = (index as i32 / self.chunks_x()) * CHUNK_SIZE;
+        (cy..(cy + CHUNK_SIZE).min(self.height))
+            .flat_map(|y| (cx..(cx + CHUNK_SIZE).min(self.width)).map(move |x| (x, y)))
+            .filter_map(|(x, y)| self.tile(TileCoord::new(x, y)))
+            .collect()
+    }
+}
+
+impl Default for Map {
+    fn default() -> Self {
+        Self::new(0, 0).expect("empty map is within bounds")
+    }
+}
+
+fn oob(coord: TileCoord) -> SimError {
+    SimError::InvalidMap(format!("tile ({}, {}) out of bounds", coord.x, coord.y))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_neighbors_are_distance_one() {
+        for c in [TileCoord::new(4, 4), TileCoord::new(5, 7)] {
+            for d in HexDir::ALL {
+                let n = c.step(d);
+                assert_eq!(c.distance(n), 1);
+                assert_eq!(n.step(d.opposite()), c);
+            }
+        }
+    }
+
+    #[test]
+    fn test_distance_across_rows() {
+        let a = TileCoord::new(0, 0);
+        assert_eq!(a.distance(TileCoord::new(3, 0)), 3);
+        assert_eq!(a.distance(TileCoord::new(0, 2)), 2);
+        assert_eq!(a.distance(TileCoord::new(1, 4)), 4);
+    }
+
+    #[test]
+    fn test_bounds_and_passability() {
+        let mut map = Map::new(4, 3).unwrap();
+        assert!(map.in_bounds(TileCoord::new(3, 2)));
+        assert!(!map.in_bounds(TileCoord::new(4, 0)));
+        assert!(!map.is_passable(TileCoord::new(-1, 0)));
+        map.set_terrain(TileCoord::new(1, 1), Terrain::Mountain)
+            .unwrap();
+        assert!(!map.is_passable(TileCoord::new(1, 1)));
+        assert_eq!(map.tile(TileCoord::new(1, 1)).unwrap().elevation, 2);
+        assert_eq!(map.neighbors(TileCoord::new(0, 0)).count(), 2);
+        assert!(Map::new(MAX_DIMENSION + 1, 1).is_err());
+    }
+
+    #[test]
+    fn test_river_visible_from_both_sides() {
+        let mut map = Map::new(5, 5).unwrap();
+        let a = TileCoord::new(2, 2);
+        map.set_river(a, HexDir::NorthEast).unwrap();
+        let b = a.step(HexDir::NorthEast);
+        assert!(map.river_between(a, b));
+        assert!(map.river_between(b, a));
+        assert!(!map.river_between(a, a.step(HexDir::East)));
+    }
+
+    #[test]
+    fn test_chunks_cover_every_tile_once() {
+        let map = Map::new(40, 20).unwrap();
+        assert_eq!(map.chunk_count(), 6);
+        let total: usize = (0..map.chunk_count())
+            .map(|i| map.chunk_tiles(i).len())
+            .sum();
+        assert_eq!(total, 800);
+        assert_eq!(map.chunk_of(TileCoord::new(39, 19)), 5);
+    }
+
+    #[test]
+    fn test_tiles_within_radius() {
+        let map = Map::with_size(MapSize::Duel);
+        assert_eq!(map.tiles_within(TileCoord::new(10, 10), 1).len(), 7);
+        assert_eq!(map.tiles_within(TileCoord::new(10, 10), 2).len(), 19);
+        assert_eq!(map.tiles_within(TileCoord::new(0, 0), 1).len(), 3);
+    }
+}
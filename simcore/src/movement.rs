@@ -0,0 +1,193 @@
+//! Unit movement
+//!
+//! A `MoveUnit` path lists the tiles entered, excluding the unit's own tile. Entering a
+//! tile costs its terrain `move_cost`, plus `river_crossing_cost` when a river runs
+//! between the two tiles. The whole path must fit in the unit's remaining AP, and the
+//! declared `ap` must equal the computed cost. A step between two enemy-controlled
+//! tiles (see `zoc`) must be the last one and spends the unit's remaining AP. Another
+//! player's city may only be entered as the last step, by a unit able to capture it
+//! (see `siege`).
+
+use crate::effects::{Event, Recorder};
+use crate::rules::Rules;
+use crate::{
+    siege, validation, zoc, PlayerId, SimError, State, TileCoord, Unit, UnitId, ValidationError,
+};
+
+/// AP to step from `from` into the adjacent tile `to`; `None` if `to` is impassable
+pub fn step_cost(state: &State, rules: &Rules, from: TileCoord, to: TileCoord) -> Option<i32> {
+    let tile = state.map.tile(to).filter(|t| t.is_passable())?;
+    let river = if state.map.river_between(from, to) {
+        rules.movement.river_crossing_cost
+    } else {
+        0
+    };
+    Some(rules.terrain(tile.terrain).move_cost + river)
+}
+
+/// Total AP for `unit` to walk `path`, checking contiguity, bounds, passability, zones of
+/// control and that no other player's unit stands in the way
+pub fn path_cost(state: &State, unit: &Unit, path: &[TileCoord]) -> Result<i32, SimError> {
+    let rules = state.rules()?;
+    if path.is_empty() {
+        return Err(ValidationError::PathNotContiguous(unit.pos).into());
+    }
+    let mut cost = 0;
+    let mut from = unit.pos;
+    for (i, &to) in path.iter().enumerate() {
+        if !from.is_adjacent(to) {
+            return Err(ValidationError::PathNotContiguous(to).into());
+        }
+        state.check_enterable(to)?;
+        if state.units_at(to).any(|u| u.owner != unit.owner) {
+            return Err(ValidationError::PathBlocked(to).into());
+        }
+        if let Some(city) = state.city_at(to).filter(|c| c.owner != unit.owner) {
+            if i + 1 < path.len() || !siege::can_capture(rules, unit, city) {
+                return Err(ValidationError::PathBlocked(to).into());
+            }
+        }
+        cost += step_cost(state, rules, from, to).ok_or(ValidationError::Impassable(to))?;
+        if i + 1 < path.len() && zoc::stops_movement(state, unit, from, to)? {
+            return Err(ValidationError::ZoneOfControl(to).into());
+        }
+        from = to;
+    }
+    Ok(cost)
+}
+
+pub(crate) fn validate_move(
+    state: &State,
+    player: PlayerId,
+    id: UnitId,
+    path: &[TileCoord],
+    ap: i32,
+) -> Result<(), SimError> {
+    let unit = state.unit(id).ok_or(ValidationError::UnitNotFound(id))?;
+    validation::check_owner(player, unit.owner)?;
+    let cost = path_cost(state, unit, path)?;
+    if cost > unit.moves_left {
+        return Err(ValidationError::InsufficientAP {
+            needed: cost,
+            available: unit.moves_left,
+        }
+        .into());
+    }
+    if ap != cost {
+        return Err(ValidationError::APMismatch {
+            declared: ap,
+            actual: cost,
+        }
+        .into());
+    }
+    let end = *path.last().expect("path_cost rejects empty paths");
+    if state
+        .unit_of_class_at(end, unit.class)
+        .is_some_and(|u| u.id != id)
+    {
+        return Err(ValidationError::OneUnitPerTileClash(end).into());
+    }
+    Ok(())
+}
+
+/// Move an already validated unit along `path`
+pub(crate) fn apply_move(
+    state: &mut State,
+    id: UnitId,
+    path: &[TileCoord],
+    ap: i32,
+    rec: &mut Recorder,
+) -> Result<(), SimError> {
+    rec.unit(state, id);
+    let Some(unit) = state.unit(id) else {
+        return Ok(());
+    };
+    let from = unit.pos;
+    let to = *path.last().expect("validated path is non-empty");
+    let last_from = path.len().checked_sub(2).map_or(from, |i| path[i]);
+    let stopped = zoc::stops_movement(state, unit, last_from, to)?;
+
+    let unit = state.units.get_mut(&id).expect("checked above");
+    unit.pos = to;
+    unit.moves_left = if stopped { 0 } else { unit.moves_left - ap };
+    unit.break_fortify();
+    let owner = unit.owner;
+    rec.emit(Event::UnitMoved { unit: id, from, to });
+    if let Some(city) = state.city_at(to).filter(|c| c.owner != owner) {
+        siege::capture(state, city.id, owner, rec)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{code, mv, new_state};
+    use crate::{apply_action, validate_action, HexDir, PlayerId, Terrain};
+
+    fn setup() -> (State, UnitId) {
+        let mut state = new_state(10, 10);
+        let map = &mut state.map;
+        map.set_terrain(TileCoord::new(4, 2), Terrain::Forest)
+            .unwrap();
+        map.set_terrain(TileCoord::new(2, 3), Terrain::Mountain)
+            .unwrap();
+        let unit = state
+            .spawn_unit(PlayerId(0), "horseman", TileCoord::new(2, 2))
+            .unwrap();
+        (state, unit)
+    }
+
+    #[test]
+    fn test_move_costs_terrain_and_rivers() {
+        let (mut state, unit) = setup();
+        let moves = state.unit(unit).unwrap().moves_left;
+        // Grassland then forest
+        let action = mv(unit, &[(3, 2), (4, 2)], 3);
+        let effects = apply_action(&mut state, PlayerId(0), action).unwrap();
+        let moved = state.unit(unit).unwrap();
+        assert_eq!(moved.pos, TileCoord::new(4, 2));
+        assert_eq!(moved.moves_left, moves - 3);
+        assert_eq!(
+            effects.events,
+            vec![Event::UnitMoved {
+                unit,
+                from: TileCoord::new(2, 2),
+                to: TileCoord::new(4, 2),
+            }]
+        );
+
+        let (mut state, unit) = setup();
+        state
+            .map
+            .set_river(TileCoord::new(2, 2), HexDir::East)
+            .unwrap();
+        assert_eq!(code(&state, &mv(unit, &[(3, 2)], 1)), Some(21));
+        assert!(validate_action(&state, PlayerId(0), &mv(unit, &[(3, 2)], 2)).is_ok());
+    }
+
+    #[test]
+    fn test_move_rejections() {
+        let (mut state, unit) = setup();
+        assert_eq!(code(&state, &mv(unit, &[], 0)), Some(19));
+        assert_eq!(code(&state, &mv(unit, &[(4, 4)], 1)), Some(19));
+        assert_eq!(code(&state, &mv(unit, &[(2, 3)], 1)), Some(6));
+        assert_eq!(code(&state, &mv(UnitId(42), &[(3, 2)], 1)), Some(1));
+        assert_eq!(
+            code(&state, &mv(unit, &[(3, 2), (4, 2), (5, 2), (6, 2)], 5)),
+            Some(8)
+        );
+
+        state
+            .spawn_unit(PlayerId(0), "warrior", TileCoord::new(3, 2))
+            .unwrap();
+        assert_eq!(code(&state, &mv(unit, &[(3, 2)], 1)), Some(7));
+        // Friendly units may be passed through
+        assert!(validate_action(&state, PlayerId(0), &mv(unit, &[(3, 2), (4, 2)], 3)).is_ok());
+
+        state
+            .spawn_unit(PlayerId(1), "builder", TileCoord::new(1, 2))
+            .unwrap();
+        assert_eq!(code(&state, &mv(unit, &[(1, 2)], 1)), Some(20));
+    }
+}
@@ -0,0 +1,314 @@
+//! Deterministic pathfinding
+//!
+//! Dijkstra over the map from one player's point of view, using the same step costs,
+//! zone-of-control and end-tile rules as `movement`. Other players' units only count
+//! when they stand on a tile the player can see (see `fog`), so a route may still be
+//! rejected by `validate_action` once hidden units are revealed.
+//!
+//! Labels are ordered by (turn, most AP left, coordinate), so equal-cost
+//! ties always resolve the same way regardless of insertion order.
+
+use crate::rules::Rules;
+use crate::{
+    fog, movement, siege, zoc, Action, City, PlayerId, SimError, State, TileCoord, Unit, UnitId,
+};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// One turn's worth of movement, ready for `Action::MoveUnit`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Route {
+    /// Tiles entered, excluding the unit's starting tile
+    pub path: Vec<TileCoord>,
+    /// AP the path costs, as declared in `MoveUnit`
+    pub ap: i32,
+}
+
+impl Route {
+    pub fn into_action(self, unit: UnitId) -> Action {
+        Action::MoveUnit {
+            unit,
+            path: self.path,
+            ap: self.ap,
+        }
+    }
+}
+
+/// Best known way to reach a tile
+#[derive(Debug, Clone, Copy)]
+struct Label {
+    turn: u32,
+    left: i32,
+    prev: TileCoord,
+    /// AP of the step from `prev`
+    cost: i32,
+}
+
+impl Label {
+    fn key(&self) -> (u32, i32) {
+        (self.turn, -self.left)
+    }
+}
+
+/// Movement queries for one player's units
+pub struct Pathfinder<'a> {
+    state: &'a State,
+    rules: &'static Rules,
+    player: PlayerId,
+    /// Tiles holding a known unit of another player
+    blocked: BTreeSet<TileCoord>,
+    /// Tiles inside a known enemy zone of control
+    controlled: BTreeSet<TileCoord>,
+}
+
+impl<'a> Pathfinder<'a> {
+    pub fn new(state: &'a State, player: PlayerId) -> Result<Self, SimError> {
+        let rules = state.rules()?;
+        let visible = fog::visible_tiles(state, player)?;
+        let mut blocked = BTreeSet::new();
+        let mut controlled = BTreeSet::new();
+        for unit in fog::known_units(state, player, &visible) {
+            if unit.owner == player {
+                continue;
+            }
+            blocked.insert(unit.pos);
+            if zoc::exerts_against(unit, player) {
+                controlled.extend(state.map.neighbors(unit.pos));
+            }
+        }
+        Ok(Self {
+            state,
+            rules,
+            player,
+            blocked,
+            controlled,
+        })
+    }
+
+    /// Cheapest route for `unit` to `target` within its remaining AP this turn
+    pub fn route(&self, unit: UnitId, target: TileCoord) -> Option<Route> {
+        self.plan(unit, target, 0)?.pop()
+    }
+
+    /// Routes for `unit` to reach `target`, one per turn starting with the current one,
+    /// taking at most `max_turns` turns beyond this one. An empty route means the unit
+    /// waits that turn.
+    pub fn plan(&self, unit: UnitId, target: TileCoord, max_turns: u32) -> Option<Vec<Route>> {
+        let unit = self.own_unit(unit)?;
+        if target == unit.pos || !self.can_end(unit, target) {
+            return None;
+        }
+        let labels = self.search(unit, max_turns, Some(target));
+        labels.get(&target)?;
+        Some(legs(&labels, unit.pos, target))
+    }
+
+    /// Every tile `unit` can end a move on this turn, with the cheapest route there
+    pub fn reachable(&self, unit: UnitId) -> BTreeMap<TileCoord, Route> {
+        let Some(unit) = self.own_unit(unit) else {
+            return BTreeMap::new();
+        };
+        let labels = self.search(unit, 0, None);
+        labels
+            .keys()
+            .filter(|&&c| c != unit.pos && self.can_end(unit, c))
+            .filter_map(|&c| Some((c, legs(&labels, unit.pos, c).pop()?)))
+            .collect()
+    }
+
+    fn hostile_city(&self, coord: TileCoord) -> Option<&'a City> {
+        self.state.city_at(coord).filter(|c| c.owner != self.player)
+    }
+
+    fn own_unit(&self, id: UnitId) -> Option<&'a Unit> {
+        self.state.unit(id).filter(|u| u.owner == self.player)
+    }
+
+    /// `unit` may stop on `coord` without breaking 1UPT against its own side
+    fn can_end(&self, unit: &Unit, coord: TileCoord) -> bool {
+        coord == unit.pos
+            || !self
+                .state
+                .units_at(coord)
+                .any(|u| u.owner == self.player && u.class == unit.class)
+    }
+
+    fn search(
+        &self,
+        unit: &Unit,
+        max_turns: u32,
+        target: Option<TileCoord>,
+    ) -> BTreeMap<TileCoord, Label> {
+        let subject = !zoc::ignores(self.rules, unit);
+        let mut labels = BTreeMap::new();
+        labels.insert(
+            unit.pos,
+            Label {
+                turn: 0,
+                left: unit.moves_left,
+                prev: unit.pos,
+                cost: 0,
+            },
+        );
+        let mut queue = BTreeSet::new();
+        queue.insert((0, -unit.moves_left, unit.pos));
+        let mut settled = BTreeSet::new();
+
+        while let Some((turn, neg_left, from)) = queue.pop_first() {
+            if !settled.insert(from) {
+                continue;
+            }
+            if Some(from) == target {
+                break;
+            }
+            // Entering a hostile city ends the move
+            if from != unit.pos && self.hostile_city(from).is_some() {
+                continue;
+            }
+            let left = -neg_left;
+            let zoc_from = subject && self.controlled.contains(&from);
+            for to in self.state.map.neighbors(from) {
+                if settled.contains(&to) || self.blocked.contains(&to) {
+                    continue;
+                }
+                if self
+                    .hostile_city(to)
+                    .is_some_and(|city| !siege::can_capture(self.rules, unit, city))
+                {
+                    continue;
+                }
+                let Some(cost) = movement::step_cost(self.state, self.rules, from, to) else {
+                    continue;
+                };
+                let stop = zoc_from && self.controlled.contains(&to);
+                let label = if cost <= left {
+                    Label {
+                        turn,
+                        left: if stop { 0 } else { left - cost },
+                        prev: from,
+                        cost,
+                    }
+                } else if turn < max_turns && cost <= unit.max_moves && self.can_end(unit, from) {
+                    Label {
+                        turn: turn + 1,
+                        left: if stop { 0 } else { unit.max_moves - cost },
+                        prev: from,
+                        cost,
+                    }
+                } else {
+                    continue;
+                };
+                if labels
+                    .get(&to)
+                    .map_or(true, |old: &Label| label.key() < old.key())
+                {
+                    labels.insert(to, label);
+                    queue.insert((label.turn, -label.left, to));
+                }
+            }
+        }
+        labels
+    }
+}
+
+/// Split the labelled path from `start` to `target` into one route per turn
+fn legs(labels: &BTreeMap<TileCoord, Label>, start: TileCoord, target: TileCoord) -> Vec<Route> {
+    let mut steps = Vec::new();
+    let mut at = target;
+    while at != start {
+        let label = labels[&at];
+        steps.push((at, label));
+        at = label.prev;
+    }
+    let mut legs = Vec::new();
+    for (coord, label) in steps.into_iter().rev() {
+        while legs.len() as u32 <= label.turn {
+            legs.push(Route {
+                path: Vec::new(),
+                ap: 0,
+            });
+        }
+        let leg = legs.last_mut().expect("pushed above");
+        leg.path.push(coord);
+        leg.ap += label.cost;
+    }
+    legs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::new_state;
+    use crate::{validate_action, Terrain};
+
+    fn setup() -> (State, UnitId) {
+        let mut state = new_state(12, 12);
+        let unit = state
+            .spawn_unit(PlayerId(0), "horseman", TileCoord::new(2, 2))
+            .unwrap();
+        (state, unit)
+    }
+
+    #[test]
+    fn test_routes_validate_and_avoid_impassable_terrain() {
+        let (mut state, unit) = setup();
+        state
+            .map
+            .set_terrain(TileCoord::new(3, 2), Terrain::Mountain)
+            .unwrap();
+        let paths = Pathfinder::new(&state, PlayerId(0)).unwrap();
+        let route = paths.route(unit, TileCoord::new(4, 2)).unwrap();
+        assert_eq!(route.ap, 3);
+        assert!(validate_action(&state, PlayerId(0), &route.into_action(unit)).is_ok());
+
+        let reachable = paths.reachable(unit);
+        assert!(!reachable.contains_key(&TileCoord::new(3, 2)));
+        for (_, route) in reachable {
+            assert!(validate_action(&state, PlayerId(0), &route.into_action(unit)).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_multi_turn_plan() {
+        let (state, unit) = setup();
+        let paths = Pathfinder::new(&state, PlayerId(0)).unwrap();
+        let target = TileCoord::new(8, 2);
+        assert_eq!(paths.route(unit, target), None);
+        let legs = paths.plan(unit, target, 1).unwrap();
+        assert_eq!(legs.iter().map(|leg| leg.ap).collect::<Vec<_>>(), [4, 2]);
+        assert_eq!(legs[1].path.last(), Some(&target));
+        assert!(validate_action(&state, PlayerId(0), &legs[0].clone().into_action(unit)).is_ok());
+    }
+
+    #[test]
+    fn test_fog_hides_blockers_and_zoc() {
+        let (mut state, unit) = setup();
+        // Visible enemy: its tile is blocked and it controls (3, 2), (4, 2) and (4, 1)
+        state
+            .spawn_unit(PlayerId(1), "warrior", TileCoord::new(3, 1))
+            .unwrap();
+        // Hidden enemy far away is ignored
+        let hidden = TileCoord::new(9, 9);
+        state.spawn_unit(PlayerId(1), "warrior", hidden).unwrap();
+
+        let paths = Pathfinder::new(&state, PlayerId(0)).unwrap();
+        assert!(!paths.reachable(unit).contains_key(&TileCoord::new(3, 1)));
+        // Walking (3, 2) -> (4, 2) -> (5, 2) would stop at (4, 2), so the 3 AP straight
+        // line loses to a detour through row 3
+        let route = paths.route(unit, TileCoord::new(5, 2)).unwrap();
+        assert_eq!(route.ap, 4);
+        assert!(validate_action(&state, PlayerId(0), &route.into_action(unit)).is_ok());
+        assert!(paths.plan(unit, hidden, 10).is_some());
+    }
+
+    #[test]
+    fn test_deterministic() {
+        let (state, unit) = setup();
+        let a = Pathfinder::new(&state, PlayerId(0))
+            .unwrap()
+            .reachable(unit);
+        let b = Pathfinder::new(&state, PlayerId(0))
+            .unwrap()
+            .reachable(unit);
+        assert_eq!(a, b);
+    }
+}
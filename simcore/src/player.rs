@@ -0,0 +1,45 @@
+//! Player entities
+
+use crate::policy::Policies;
+use crate::tech::Freeze;
+use crate::{Fixed, PlayerId};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// A participant in the match
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Player {
+    pub id: PlayerId,
+    pub name: String,
+    /// Techs already researched
+    pub known_techs: BTreeSet<String>,
+    /// Tech that receives this player's science
+    pub researching: Option<String>,
+    /// Science banked per tech; switching research keeps partial progress
+    pub research_progress: BTreeMap<String, Fixed>,
+    /// Techs whose effects and research are suspended, known or not
+    pub frozen_techs: BTreeMap<String, Freeze>,
+    /// Treasury
+    pub gold: Fixed,
+    /// Culture and influence banked from yields
+    pub culture: Fixed,
+    pub influence: Fixed,
+    pub policies: Policies,
+}
+
+impl Player {
+    pub fn new(id: PlayerId, name: impl Into<String>) -> Self {
+        Self {
+            id,
+            name: name.into(),
+            known_techs: BTreeSet::new(),
+            researching: None,
+            research_progress: BTreeMap::new(),
+            frozen_techs: BTreeMap::new(),
+            gold: Fixed::ZERO,
+            culture: Fixed::ZERO,
+            influence: Fixed::ZERO,
+            policies: Policies::default(),
+        }
+    }
+}
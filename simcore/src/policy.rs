@@ -0,0 +1,301 @@
+//! Government policies
+//!
+//! The rules list a fixed row of slots, each taking cards of one category; later slots
+//! open up with techs. Filling an empty slot is free, replacing a card costs gold, and
+//! either way the slot is locked for a few turns afterwards. Slotted cards add their
+//! yield and combat bonuses for as long as the player has the card's tech; a card whose
+//! tech is frozen stays slotted but gives nothing.
+
+use crate::effects::{Event, Recorder};
+use crate::rules::{PolicyDef, PolicyModifiers, Rules};
+use crate::{tech, Fixed, Player, PlayerId, SimError, State, ValidationError};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// A player's slotted cards
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Policies {
+    /// Card in each filled slot
+    pub slotted: BTreeMap<i32, String>,
+    /// First turn each recently changed slot can change again
+    pub ready_on: BTreeMap<i32, i32>,
+}
+
+impl Policies {
+    /// Slot holding `id`, if any
+    pub fn slot_of(&self, id: &str) -> Option<i32> {
+        self.slotted
+            .iter()
+            .find(|(_, card)| card.as_str() == id)
+            .map(|(slot, _)| *slot)
+    }
+}
+
+/// `player` has the tech behind `requires`, or nothing is required
+fn unlocked(player: &Player, requires: &Option<String>) -> bool {
+    requires.as_deref().map_or(true, |t| tech::has(player, t))
+}
+
+/// `slot` exists and `player` has the tech that opens it
+fn slot_open(rules: &Rules, player: &Player, slot: i32) -> bool {
+    rules
+        .policy_slot(slot)
+        .is_some_and(|def| unlocked(player, &def.requires_tech))
+}
+
+/// Slots open to `player`, by slot number
+pub fn open_slots(rules: &Rules, player: &Player) -> Vec<i32> {
+    (0..rules.policy.slots.len() as i32)
+        .filter(|&slot| slot_open(rules, player, slot))
+        .collect()
+}
+
+/// `player`'s slotted cards that are in effect, in slot order
+///
+/// A card counts only while both it and the slot holding it are unlocked.
+pub fn active_cards<'a>(
+    rules: &'a Rules,
+    player: &'a Player,
+) -> impl Iterator<Item = (&'a str, &'a PolicyDef)> + 'a {
+    player
+        .policies
+        .slotted
+        .iter()
+        .filter(|(&slot, _)| slot_open(rules, player, slot))
+        .filter_map(|(_, id)| Some((id.as_str(), rules.policy(id)?)))
+        .filter(|(_, def)| unlocked(player, &def.requires_tech))
+}
+
+/// Combined bonuses of `player`'s active cards
+pub fn modifiers(rules: &Rules, player: &Player) -> PolicyModifiers {
+    active_cards(rules, player).fold(PolicyModifiers::default(), |acc, (_, def)| {
+        acc + def.modifiers
+    })
+}
+
+/// Policy combat bonus for `owner`'s units and cities; zero off the roster
+pub(crate) fn combat_pct(state: &State, rules: &Rules, owner: PlayerId) -> i32 {
+    state
+        .player(owner)
+        .map_or(0, |p| modifiers(rules, p).combat_pct)
+}
+
+pub(crate) fn validate_set(
+    state: &State,
+    player: PlayerId,
+    slot: i32,
+    id: &str,
+) -> Result<(), SimError> {
+    let rules = state.rules()?;
+    let player = state
+        .player(player)
+        .ok_or(ValidationError::PlayerNotFound(player))?;
+    let slot_def = rules
+        .policy_slot(slot)
+        .filter(|def| unlocked(player, &def.requires_tech))
+        .ok_or(ValidationError::PolicySlotInvalid(slot))?;
+    let card = rules
+        .policy(id)
+        .ok_or_else(|| ValidationError::UnknownPolicy(id.to_string()))?;
+    if !unlocked(player, &card.requires_tech) {
+        return Err(ValidationError::PolicyLocked(id.to_string()).into());
+    }
+    let policies = &player.policies;
+    if policies.slot_of(id).is_some() {
+        return Err(ValidationError::PolicyAlreadySlotted(id.to_string()).into());
+    }
+    if card.category != slot_def.category {
+        return Err(ValidationError::PolicyCategoryMismatch {
+            slot,
+            id: id.to_string(),
+        }
+        .into());
+    }
+    if let Some(&ready_on) = policies.ready_on.get(&slot) {
+        if state.turn < ready_on {
+            return Err(ValidationError::PolicySlotCooldown { slot, ready_on }.into());
+        }
+    }
+    if policies.slotted.contains_key(&slot) {
+        let cost = rules.policy.swap_cost;
+        if player.gold < Fixed::from_int(cost) {
+            return Err(ValidationError::InsufficientFunds {
+                needed: cost,
+                available: player.gold.floor(),
+            }
+            .into());
+        }
+    }
+    Ok(())
+}
+
+/// Slot an already validated card for `player`, paying for any swap
+pub(crate) fn apply_set(
+    state: &mut State,
+    player: PlayerId,
+    slot: i32,
+    id: String,
+    rec: &mut Recorder,
+) -> Result<(), SimError> {
+    let rules = state.rules()?;
+    rec.player(state, player);
+    let turn = state.turn;
+    let Some(p) = state.players.get_mut(&player) else {
+        return Ok(());
+    };
+    if p.policies.slotted.contains_key(&slot) {
+        p.gold -= Fixed::from_int(rules.policy.swap_cost);
+    }
+    p.policies.slotted.insert(slot, id.clone());
+    if rules.policy.swap_cooldown > 0 {
+        p.policies
+            .ready_on
+            .insert(slot, turn + rules.policy.swap_cooldown);
+    }
+    rec.emit(Event::PolicySet { player, slot, id });
+    Ok(())
+}
+
+/// Upkeep: forget cooldowns that have run out
+pub(crate) fn expire_cooldowns(state: &mut State) {
+    let turn = state.turn + 1;
+    for player in state.players.values_mut() {
+        player
+            .policies
+            .ready_on
+            .retain(|_, ready_on| *ready_on > turn);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::code;
+    use crate::{apply_action, end_turn, enumerate_legal_actions, validate_action, Action};
+
+    fn setup() -> State {
+        let mut state = State::new();
+        state.add_player(PlayerId(0), "Ana");
+        state
+    }
+
+    fn set(slot: i32, id: &str) -> Action {
+        Action::SetPolicy {
+            slot,
+            id: id.into(),
+        }
+    }
+
+    #[test]
+    fn test_slots_and_cards_are_checked() {
+        let state = setup();
+        assert_eq!(code(&state, &set(7, "discipline")), Some(11));
+        assert_eq!(code(&state, &set(-1, "discipline")), Some(11));
+        // Slot 2 needs bronze working
+        assert_eq!(code(&state, &set(2, "discipline")), Some(11));
+        assert_eq!(code(&state, &set(0, "anarchy")), Some(32));
+        assert_eq!(code(&state, &set(0, "agoge")), Some(33));
+        assert_eq!(code(&state, &set(1, "discipline")), Some(34));
+        assert!(validate_action(&state, PlayerId(0), &set(0, "discipline")).is_ok());
+        assert_eq!(
+            open_slots(state.rules().unwrap(), &state.players[&PlayerId(0)]),
+            [0, 1]
+        );
+    }
+
+    #[test]
+    fn test_swaps_cost_gold_and_cool_down() {
+        let mut state = setup();
+        apply_action(&mut state, PlayerId(0), set(1, "god_king")).unwrap();
+        assert_eq!(code(&state, &set(0, "god_king")), Some(35));
+        let player = state.players.get_mut(&PlayerId(0)).unwrap();
+        player
+            .known_techs
+            .extend(["foraging", "pottery", "agriculture"].map(String::from));
+        player.gold = Fixed::from_int(30);
+
+        let swap = set(1, "urban_planning");
+        assert_eq!(code(&state, &swap), Some(36));
+        for _ in 0..3 {
+            end_turn(&mut state).unwrap();
+        }
+        assert!(state.players[&PlayerId(0)].policies.ready_on.is_empty());
+        state.players.get_mut(&PlayerId(0)).unwrap().gold = Fixed::from_int(10);
+        assert_eq!(code(&state, &swap), Some(9));
+        state.players.get_mut(&PlayerId(0)).unwrap().gold = Fixed::from_int(30);
+        let effects = apply_action(&mut state, PlayerId(0), swap).unwrap();
+        assert_eq!(effects.events[0].kind(), "PolicySet");
+        let player = &state.players[&PlayerId(0)];
+        assert_eq!(player.gold, Fixed::from_int(5));
+        assert_eq!(player.policies.slot_of("urban_planning"), Some(1));
+    }
+
+    #[test]
+    fn test_cards_follow_the_acting_player() {
+        let mut state = setup();
+        state.add_player(PlayerId(1), "Bo");
+        apply_action(&mut state, PlayerId(0), set(0, "discipline")).unwrap();
+        assert_eq!(code(&state, &set(0, "discipline")), Some(35));
+        assert!(validate_action(&state, PlayerId(1), &set(0, "discipline")).is_ok());
+
+        let listed = |player| -> Vec<Action> {
+            enumerate_legal_actions(&state, player)
+                .into_iter()
+                .filter(|a| matches!(a, Action::SetPolicy { .. }))
+                .collect()
+        };
+        assert!(!listed(PlayerId(0)).contains(&set(0, "discipline")));
+        assert!(listed(PlayerId(1)).contains(&set(0, "discipline")));
+        for action in listed(PlayerId(1)) {
+            assert!(validate_action(&state, PlayerId(1), &action).is_ok());
+        }
+        assert!(state.players[&PlayerId(1)].policies.slotted.is_empty());
+    }
+
+    #[test]
+    fn test_modifiers_need_the_card_tech() {
+        let mut state = setup();
+        let rules = state.rules().unwrap();
+        let player = state.players.get_mut(&PlayerId(0)).unwrap();
+        player.known_techs.insert("agriculture".into());
+        player.policies.slotted.insert(0, "discipline".into());
+        player.policies.slotted.insert(1, "urban_planning".into());
+        let mods = modifiers(rules, player);
+        assert_eq!((mods.combat_pct, mods.production_pct), (5, 10));
+
+        crate::freeze_tech(
+            &mut state,
+            PlayerId(0),
+            "agriculture",
+            tech::FreezeCause::ClimateShock,
+            None,
+        )
+        .unwrap();
+        let mods = modifiers(rules, &state.players[&PlayerId(0)]);
+        assert_eq!((mods.combat_pct, mods.production_pct), (5, 0));
+        assert_eq!(combat_pct(&state, rules, PlayerId(0)), 5);
+        assert_eq!(combat_pct(&state, rules, PlayerId(4)), 0);
+    }
+
+    #[test]
+    fn test_modifiers_need_the_slot_tech() {
+        let mut state = setup();
+        let rules = state.rules().unwrap();
+        let player = state.players.get_mut(&PlayerId(0)).unwrap();
+        player.known_techs.insert("bronze_working".into());
+        player.policies.slotted.insert(2, "discipline".into());
+        assert_eq!(modifiers(rules, player).combat_pct, 5);
+
+        crate::freeze_tech(
+            &mut state,
+            PlayerId(0),
+            "bronze_working",
+            tech::FreezeCause::Sanctions,
+            None,
+        )
+        .unwrap();
+        let player = &state.players[&PlayerId(0)];
+        assert_eq!(open_slots(rules, player), [0, 1]);
+        assert_eq!(modifiers(rules, player).combat_pct, 0);
+    }
+}
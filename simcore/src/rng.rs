@@ -0,0 +1,160 @@
+//! Seeded, counter-based RNG
+//!
+//! Every system draws from its own named stream. The n-th draw of a stream is a pure
+//! function of `(seed, stream, n)` (SplitMix64 keyed per stream), so the generator is
+//! portable, serializes as a handful of integers, and a new draw in one system never
+//! shifts the sequence another system sees.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+const GOLDEN_GAMMA: u64 = 0x9E37_79B9_7F4A_7C15;
+
+/// Named RNG sub-stream, one per system
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum RngStream {
+    MapGen,
+    Combat,
+    Events,
+    Ai,
+    Diplomacy,
+}
+
+impl RngStream {
+    /// Stable per-stream key; independent of declaration order so streams can be added
+    fn key(self) -> u64 {
+        match self {
+            RngStream::MapGen => 0x6D61_7067_656E_0001,
+            RngStream::Combat => 0x636F_6D62_6174_0002,
+            RngStream::Events => 0x6576_656E_7473_0003,
+            RngStream::Ai => 0x6169_0000_0000_0004,
+            RngStream::Diplomacy => 0x6469_706C_6F6D_0005,
+        }
+    }
+}
+
+fn splitmix64(mut z: u64) -> u64 {
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+/// Match RNG: a seed plus a draw counter per stream
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Rng {
+    seed: u64,
+    counters: BTreeMap<RngStream, u64>,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            counters: BTreeMap::new(),
+        }
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Draws taken so far from `stream`
+    pub fn draws(&self, stream: RngStream) -> u64 {
+        self.counters.get(&stream).copied().unwrap_or(0)
+    }
+
+    /// The value draw number `index` of `stream` yields, without consuming it
+    pub fn peek_at(&self, stream: RngStream, index: u64) -> u64 {
+        let base = splitmix64(self.seed ^ stream.key());
+        splitmix64(base.wrapping_add(index.wrapping_add(1).wrapping_mul(GOLDEN_GAMMA)))
+    }
+
+    pub fn next_u64(&mut self, stream: RngStream) -> u64 {
+        let index = self.draws(stream);
+        self.counters.insert(stream, index + 1);
+        self.peek_at(stream, index)
+    }
+
+    /// Uniform value in `0..bound`; `bound` must be non-zero
+    pub fn below(&mut self, stream: RngStream, bound: u64) -> u64 {
+        assert!(bound > 0, "Rng::below bound must be non-zero");
+        // Reject the biased tail so every value is equally likely
+        let zone = u64::MAX - u64::MAX % bound;
+        loop {
+            let v = self.next_u64(stream);
+            if v < zone {
+                return v % bound;
+            }
+        }
+    }
+
+    /// True with probability `pct`/100
+    pub fn chance_pct(&mut self, stream: RngStream, pct: u32) -> bool {
+        self.below(stream, 100) < pct as u64
+    }
+}
+
+impl Default for Rng {
+    fn default() -> Self {
+        Self::new(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_same_seed_same_sequence() {
+        let mut a = Rng::new(42);
+        let mut b = Rng::new(42);
+        for _ in 0..16 {
+            assert_eq!(a.next_u64(RngStream::Combat), b.next_u64(RngStream::Combat));
+        }
+        assert_ne!(
+            Rng::new(1).next_u64(RngStream::Combat),
+            Rng::new(2).next_u64(RngStream::Combat)
+        );
+    }
+
+    #[test]
+    fn test_streams_do_not_perturb_each_other() {
+        let mut a = Rng::new(7);
+        let mut b = Rng::new(7);
+        for _ in 0..5 {
+            b.next_u64(RngStream::Combat);
+        }
+        assert_eq!(a.next_u64(RngStream::Events), b.next_u64(RngStream::Events));
+        assert_ne!(a.peek_at(RngStream::Events, 0), a.peek_at(RngStream::Ai, 0));
+    }
+
+    #[test]
+    fn test_serialized_rng_resumes_sequence() {
+        let mut rng = Rng::new(99);
+        rng.next_u64(RngStream::MapGen);
+        let json = serde_json::to_string(&rng).unwrap();
+        let mut restored: Rng = serde_json::from_str(&json).unwrap();
+        assert_eq!(
+            rng.next_u64(RngStream::MapGen),
+            restored.next_u64(RngStream::MapGen)
+        );
+    }
+
+    #[test]
+    fn test_below_stays_in_range() {
+        let mut rng = Rng::new(3);
+        for bound in [1, 2, 6, 100] {
+            for _ in 0..50 {
+                assert!(rng.below(RngStream::Ai, bound) < bound);
+            }
+        }
+    }
+
+    #[test]
+    fn test_known_first_draw() {
+        // Pinned so the generator cannot silently change across platforms or releases
+        assert_eq!(Rng::new(0).peek_at(RngStream::Combat, 0), KNOWN_FIRST_DRAW);
+    }
+
+    const KNOWN_FIRST_DRAW: u64 = 752_333_553_486_011_401;
+}
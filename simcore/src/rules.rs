@@ -0,0 +1,693 @@
+//! Data-driven rules database
+//!
+//! Rulesets are versioned JSON files under `simcore/rules/`, embedded at build time and
+//! validated on first load. A `State` refers to its ruleset only by `rules_ver`, so a
+//! match stays pinned to the exact data it was created with.
+
+use crate::{Resource, Terrain, UnitClass};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::OnceLock;
+use thiserror::Error;
+
+/// Ruleset used when a match does not ask for a specific version
+pub const DEFAULT_VERSION: &str = "0.1.0";
+
+/// Embedded ruleset files, keyed by version
+const BUILTIN: &[(&str, &str)] = &[("0.1.0", include_str!("../rules/0.1.0.json"))];
+
+/// Rules loading/validation error
+#[derive(Error, Debug)]
+pub enum RulesError {
+    #[error("Unknown rules version: {0}")]
+    UnknownVersion(String),
+    #[error("Rules parse error: {0}")]
+    Parse(#[from] serde_json::Error),
+    #[error("Rules validation failed: {0}")]
+    Invalid(String),
+}
+
+/// Unit kind definition
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UnitDef {
+    pub class: UnitClass,
+    pub cost: i32,
+    pub moves: i32,
+    pub strength: i32,
+    #[serde(default)]
+    pub ranged_strength: i32,
+    #[serde(default)]
+    pub range: i32,
+    #[serde(default)]
+    pub requires_tech: Option<String>,
+    /// Moves through enemy zones of control freely
+    #[serde(default)]
+    pub ignores_zoc: bool,
+}
+
+impl UnitDef {
+    pub fn is_ranged(&self) -> bool {
+        self.range > 0
+    }
+}
+
+/// District kind definition
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DistrictDef {
+    pub cost: i32,
+    #[serde(default)]
+    pub requires_tech: Option<String>,
+    /// Terrains the district can be placed on; empty allows any passable land
+    #[serde(default)]
+    pub terrain: Vec<Terrain>,
+    /// Yield the district's adjacency bonus is paid in
+    pub output: YieldKind,
+    #[serde(default)]
+    pub adjacency: Vec<AdjacencyDef>,
+}
+
+/// A city yield
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum YieldKind {
+    Food,
+    Production,
+    Gold,
+    Science,
+    Culture,
+    Influence,
+}
+
+impl YieldKind {
+    pub const ALL: [YieldKind; 6] = [
+        YieldKind::Food,
+        YieldKind::Production,
+        YieldKind::Gold,
+        YieldKind::Science,
+        YieldKind::Culture,
+        YieldKind::Influence,
+    ];
+}
+
+/// Whole amounts of each yield; missing entries are zero
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct YieldTable {
+    #[serde(default)]
+    pub food: i32,
+    #[serde(default)]
+    pub production: i32,
+    #[serde(default)]
+    pub gold: i32,
+    #[serde(default)]
+    pub science: i32,
+    #[serde(default)]
+    pub culture: i32,
+    #[serde(default)]
+    pub influence: i32,
+}
+
+impl YieldTable {
+    pub fn get(&self, kind: YieldKind) -> i32 {
+        match kind {
+            YieldKind::Food => self.food,
+            YieldKind::Production => self.production,
+            YieldKind::Gold => self.gold,
+            YieldKind::Science => self.science,
+            YieldKind::Culture => self.culture,
+            YieldKind::Influence => self.influence,
+        }
+    }
+}
+
+impl std::ops::Add for YieldTable {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self {
+        Self {
+            food: self.food + rhs.food,
+            production: self.production + rhs.production,
+            gold: self.gold + rhs.gold,
+            science: self.science + rhs.science,
+            culture: self.culture + rhs.culture,
+            influence: self.influence + rhs.influence,
+        }
+    }
+}
+
+/// What a district counts among its neighboring tiles
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AdjacencySource {
+    Terrain(Terrain),
+    /// A district of this kind
+    District(String),
+    /// Any district or city center
+    AnyDistrict,
+    /// A river along the shared edge
+    River,
+}
+
+/// `bonus` of the district's output for every `per` matching neighbors
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AdjacencyDef {
+    pub source: AdjacencySource,
+    pub bonus: i32,
+    #[serde(default = "one")]
+    pub per: i32,
+}
+
+fn one() -> i32 {
+    1
+}
+
+/// Technology definition
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TechDef {
+    pub cost: i32,
+    pub tier: u8,
+    pub era: String,
+    #[serde(default)]
+    pub prereqs: Vec<String>,
+    /// Flat yields every city of a player who has the tech receives
+    #[serde(default)]
+    pub city_yields: YieldTable,
+}
+
+/// Policy card category
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum PolicyCategory {
+    Military,
+    Economic,
+    Diplomatic,
+}
+
+/// Policy card definition
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PolicyDef {
+    pub category: PolicyCategory,
+    #[serde(default)]
+    pub requires_tech: Option<String>,
+    #[serde(default)]
+    pub modifiers: PolicyModifiers,
+}
+
+/// Percentage bonuses a slotted card gives its owner
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PolicyModifiers {
+    #[serde(default)]
+    pub food_pct: i32,
+    #[serde(default)]
+    pub production_pct: i32,
+    #[serde(default)]
+    pub gold_pct: i32,
+    #[serde(default)]
+    pub science_pct: i32,
+    #[serde(default)]
+    pub culture_pct: i32,
+    #[serde(default)]
+    pub influence_pct: i32,
+    /// Strength of the owner's units and cities, attacking or defending
+    #[serde(default)]
+    pub combat_pct: i32,
+}
+
+impl PolicyModifiers {
+    /// Bonus to `kind`, in percent
+    pub fn pct(&self, kind: YieldKind) -> i32 {
+        match kind {
+            YieldKind::Food => self.food_pct,
+            YieldKind::Production => self.production_pct,
+            YieldKind::Gold => self.gold_pct,
+            YieldKind::Science => self.science_pct,
+            YieldKind::Culture => self.culture_pct,
+            YieldKind::Influence => self.influence_pct,
+        }
+    }
+
+    fn values(&self) -> [i32; 7] {
+        [
+            self.food_pct,
+            self.production_pct,
+            self.gold_pct,
+            self.science_pct,
+            self.culture_pct,
+            self.influence_pct,
+            self.combat_pct,
+        ]
+    }
+}
+
+impl std::ops::Add for PolicyModifiers {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self {
+        Self {
+            food_pct: self.food_pct + rhs.food_pct,
+            production_pct: self.production_pct + rhs.production_pct,
+            gold_pct: self.gold_pct + rhs.gold_pct,
+            science_pct: self.science_pct + rhs.science_pct,
+            culture_pct: self.culture_pct + rhs.culture_pct,
+            influence_pct: self.influence_pct + rhs.influence_pct,
+            combat_pct: self.combat_pct + rhs.combat_pct,
+        }
+    }
+}
+
+/// A government slot; slot numbers in `SetPolicy` index the rules' slot list
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PolicySlotDef {
+    pub category: PolicyCategory,
+    #[serde(default)]
+    pub requires_tech: Option<String>,
+}
+
+/// Government slots and the price of changing them
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PolicyConstants {
+    pub slots: Vec<PolicySlotDef>,
+    /// Gold to replace a card; filling an empty slot is free
+    pub swap_cost: i32,
+    /// Turns a slot stays locked after its card changes
+    pub swap_cooldown: i32,
+}
+
+/// Per-terrain yields, movement cost and defense modifier
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TerrainDef {
+    pub food: i32,
+    pub production: i32,
+    pub gold: i32,
+    /// AP to enter; ignored for impassable terrain
+    pub move_cost: i32,
+    pub defense_pct: i32,
+}
+
+impl TerrainDef {
+    pub fn yields(&self) -> YieldTable {
+        YieldTable {
+            food: self.food,
+            production: self.production,
+            gold: self.gold,
+            ..Default::default()
+        }
+    }
+}
+
+/// Yields that do not come from tiles
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct YieldConstants {
+    /// Flat bonus on top of the city center tile
+    pub city_center: YieldTable,
+    /// Per citizen, in hundredths
+    pub per_citizen_pct: YieldTable,
+    /// Per player, independent of cities
+    pub player_base: YieldTable,
+}
+
+/// Movement costs beyond per-terrain `move_cost`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MovementConstants {
+    /// Extra AP to cross a river between two tiles
+    pub river_crossing_cost: i32,
+}
+
+/// City footprint
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CityConstants {
+    /// Districts go on tiles within this distance of the city center
+    pub workable_radius: i32,
+}
+
+/// Sight radii, in tiles, for fog of war
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VisionConstants {
+    pub unit_sight: i32,
+    pub city_sight: i32,
+}
+
+/// Territory and supply reach, in tiles
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SupplyConstants {
+    /// Tiles within this distance of a city are its owner's territory
+    pub territory_radius: i32,
+    /// Steps a supply line reaches beyond friendly territory
+    pub supply_range: i32,
+}
+
+/// Combat constants from the Combat contract, as integer percentages
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CombatConstants {
+    pub k_melee: i32,
+    pub k_ranged: i32,
+    pub alpha_pct: i32,
+    pub flank_step_pct: i32,
+    pub flank_cap_pct: i32,
+    pub river_penalty_pct: i32,
+    pub high_ground_def_pct: i32,
+    pub fortify_per_turn_pct: i32,
+    pub fortify_cap_pct: i32,
+    pub oos_per_turn_pct: i32,
+    pub oos_cap_pct: i32,
+    pub city_def_pct: i32,
+    /// Cap on combined terrain + fortification defense
+    pub terrain_cap_pct: i32,
+    /// City defense strength: base plus a bonus per citizen
+    pub city_strength_base: i32,
+    pub city_strength_per_pop: i32,
+    /// Wall HP; the city is breached once walls reach zero
+    pub city_wall_hp: i32,
+    /// Wall HP repaired each turn the city is not under siege
+    pub city_wall_repair: i32,
+    /// Reach of a city's ranged strike
+    pub city_range: i32,
+}
+
+impl CombatConstants {
+    /// Fortify turns after which the bonus stops growing
+    pub fn max_fortify_turns(&self) -> u8 {
+        turn_limit(self.fortify_cap_pct, self.fortify_per_turn_pct).unwrap_or(u8::MAX)
+    }
+
+    /// Out-of-supply turns after which the penalty stops growing
+    pub fn max_oos_turns(&self) -> u8 {
+        turn_limit(self.oos_cap_pct, self.oos_per_turn_pct).unwrap_or(u8::MAX)
+    }
+}
+
+/// Steps of `step` needed to reach `cap`, or `None` if that does not fit a `u8`
+///
+/// Validated rules always fit.
+fn turn_limit(cap: i32, step: i32) -> Option<u8> {
+    u8::try_from(ceil_div(cap, step)).ok()
+}
+
+fn ceil_div(a: i32, b: i32) -> i32 {
+    (a + b - b.signum()) / b
+}
+
+/// A complete, validated ruleset
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Rules {
+    pub version: String,
+    pub units: BTreeMap<String, UnitDef>,
+    pub districts: BTreeMap<String, DistrictDef>,
+    pub techs: BTreeMap<String, TechDef>,
+    pub policies: BTreeMap<String, PolicyDef>,
+    pub terrain: BTreeMap<Terrain, TerrainDef>,
+    /// Extra yields of a tile holding the resource
+    #[serde(default)]
+    pub resources: BTreeMap<Resource, YieldTable>,
+    pub movement: MovementConstants,
+    pub city: CityConstants,
+    pub yields: YieldConstants,
+    pub vision: VisionConstants,
+    pub supply: SupplyConstants,
+    pub policy: PolicyConstants,
+    pub combat: CombatConstants,
+}
+
+impl Rules {
+    /// Look up an embedded ruleset by version
+    pub fn load(version: &str) -> Result<&'static Rules, RulesError> {
+        static REGISTRY: OnceLock<BTreeMap<&'static str, Rules>> = OnceLock::new();
+        REGISTRY
+            .get_or_init(|| {
+                BUILTIN
+                    .iter()
+                    .map(|(ver, json)| {
+                        let rules = Rules::from_json(json)
+                            .unwrap_or_else(|e| panic!("embedded rules {} invalid: {}", ver, e));
+                        assert_eq!(rules.version, *ver, "embedded rules version mismatch");
+                        (*ver, rules)
+                    })
+                    .collect()
+            })
+            .get(version)
+            .ok_or_else(|| RulesError::UnknownVersion(version.to_string()))
+    }
+
+    /// Versions available to `load`
+    pub fn versions() -> impl Iterator<Item = &'static str> {
+        BUILTIN.iter().map(|(ver, _)| *ver)
+    }
+
+    /// Parse and validate a ruleset
+    pub fn from_json(json: &str) -> Result<Rules, RulesError> {
+        let rules: Rules = serde_json::from_str(json)?;
+        rules.validate()?;
+        Ok(rules)
+    }
+
+    pub fn unit(&self, kind: &str) -> Option<&UnitDef> {
+        self.units.get(kind)
+    }
+
+    pub fn district(&self, kind: &str) -> Option<&DistrictDef> {
+        self.districts.get(kind)
+    }
+
+    pub fn tech(&self, id: &str) -> Option<&TechDef> {
+        self.techs.get(id)
+    }
+
+    pub fn policy(&self, id: &str) -> Option<&PolicyDef> {
+        self.policies.get(id)
+    }
+
+    pub fn policy_slot(&self, slot: i32) -> Option<&PolicySlotDef> {
+        usize::try_from(slot)
+            .ok()
+            .and_then(|i| self.policy.slots.get(i))
+    }
+
+    pub fn terrain(&self, terrain: Terrain) -> &TerrainDef {
+        &self.terrain[&terrain]
+    }
+
+    /// Check cross-references and value ranges
+    pub fn validate(&self) -> Result<(), RulesError> {
+        let invalid = |msg: String| Err(RulesError::Invalid(msg));
+        if self.version.is_empty() {
+            return invalid("empty version".to_string());
+        }
+
+        let check_tech = |what: &str, tech: &Option<String>| match tech {
+            Some(t) if !self.techs.contains_key(t) => Err(RulesError::Invalid(format!(
+                "{} requires unknown tech {}",
+                what, t
+            ))),
+            _ => Ok(()),
+        };
+        for (kind, def) in &self.units {
+            if def.cost <= 0 || def.moves <= 0 || def.strength < 0 {
+                return invalid(format!(
+                    "unit {} has non-positive cost/moves or negative strength",
+                    kind
+                ));
+            }
+            if def.class == UnitClass::Combat && def.strength == 0 {
+                return invalid(format!("combat unit {} has no strength", kind));
+            }
+            if def.is_ranged() != (def.ranged_strength > 0) {
+                return invalid(format!("unit {} range and ranged_strength disagree", kind));
+            }
+            check_tech(kind, &def.requires_tech)?;
+        }
+        for (kind, def) in &self.districts {
+            if def.cost <= 0 {
+                return invalid(format!("district {} has non-positive cost", kind));
+            }
+            check_tech(kind, &def.requires_tech)?;
+            for adj in &def.adjacency {
+                if adj.per < 1 {
+                    return invalid(format!("district {} counts adjacency per < 1", kind));
+                }
+                if let AdjacencySource::District(other) = &adj.source {
+                    if !self.districts.contains_key(other) {
+                        return invalid(format!(
+                            "district {} counts unknown district {}",
+                            kind, other
+                        ));
+                    }
+                }
+            }
+        }
+        for (id, def) in &self.policies {
+            check_tech(id, &def.requires_tech)?;
+            if def.modifiers.values().iter().any(|&pct| pct <= -100) {
+                return invalid(format!("policy {} removes a whole yield", id));
+            }
+        }
+        for slot in &self.policy.slots {
+            check_tech("policy slot", &slot.requires_tech)?;
+        }
+        if self.policy.swap_cost < 0 || self.policy.swap_cooldown < 0 {
+            return invalid("policy swap cost or cooldown is negative".to_string());
+        }
+        for (id, def) in &self.techs {
+            if def.cost <= 0 {
+                return invalid(format!("tech {} has non-positive cost", id));
+            }
+            for pre in &def.prereqs {
+                match self.techs.get(pre) {
+                    None => return invalid(format!("tech {} has unknown prereq {}", id, pre)),
+                    Some(p) if p.tier >= def.tier => {
+                        // Strictly increasing tiers also rules out prerequisite cycles
+                        return invalid(format!("tech {} prereq {} is not a lower tier", id, pre));
+                    }
+                    Some(_) => {}
+                }
+            }
+        }
+
+        let covered: BTreeSet<Terrain> = self.terrain.keys().copied().collect();
+        for terrain in ALL_TERRAIN {
+            if !covered.contains(&terrain) {
+                return invalid(format!("missing terrain definition for {:?}", terrain));
+            }
+            if terrain.is_passable() && self.terrain[&terrain].move_cost < 1 {
+                return invalid(format!("passable terrain {:?} has move_cost < 1", terrain));
+            }
+        }
+
+        if self.movement.river_crossing_cost < 0 {
+            return invalid("river crossing cost is negative".to_string());
+        }
+        // Only policy percentages may take yields away
+        let y = &self.yields;
+        let tables = self
+            .terrain
+            .values()
+            .map(TerrainDef::yields)
+            .chain(self.resources.values().copied())
+            .chain(self.techs.values().map(|t| t.city_yields))
+            .chain([y.city_center, y.per_citizen_pct, y.player_base]);
+        for table in tables {
+            if YieldKind::ALL.iter().any(|&k| table.get(k) < 0) {
+                return invalid("flat yields must not be negative".to_string());
+            }
+        }
+        if self.city.workable_radius < 1 {
+            return invalid("city workable radius must be positive".to_string());
+        }
+        if self.vision.unit_sight < 0 || self.vision.city_sight < 0 {
+            return invalid("sight radius is negative".to_string());
+        }
+        if self.supply.territory_radius < 0 || self.supply.supply_range < 0 {
+            return invalid("territory or supply range is negative".to_string());
+        }
+
+        let c = &self.combat;
+        if c.k_melee <= 0 || c.k_ranged <= 0 || c.alpha_pct <= 0 {
+            return invalid("combat K and alpha must be positive".to_string());
+        }
+        if c.fortify_per_turn_pct <= 0 || c.oos_per_turn_pct >= 0 || c.flank_step_pct <= 0 {
+            return invalid("combat per-step modifiers have the wrong sign".to_string());
+        }
+        if c.fortify_cap_pct < 0 || c.oos_cap_pct > 0 {
+            return invalid("fortify cap is negative or out-of-supply cap is positive".to_string());
+        }
+        if turn_limit(c.fortify_cap_pct, c.fortify_per_turn_pct).is_none()
+            || turn_limit(c.oos_cap_pct, c.oos_per_turn_pct).is_none()
+        {
+            return invalid("fortify or out-of-supply cap takes over 255 turns".to_string());
+        }
+        if c.city_strength_base <= 0 || c.city_wall_hp <= 0 || c.city_range < 1 {
+            return invalid("city base strength, wall HP and range must be positive".to_string());
+        }
+        if c.city_strength_per_pop < 0 || c.city_wall_repair < 0 {
+            return invalid(
+                "city strength per pop and wall repair must not be negative".to_string(),
+            );
+        }
+        Ok(())
+    }
+}
+
+const ALL_TERRAIN: [Terrain; 11] = [
+    Terrain::Grassland,
+    Terrain::Plains,
+    Terrain::Desert,
+    Terrain::Tundra,
+    Terrain::Snow,
+    Terrain::Forest,
+    Terrain::Hills,
+    Terrain::Marsh,
+    Terrain::Mountain,
+    Terrain::Coast,
+    Terrain::Ocean,
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builtin_rules_load() {
+        for version in Rules::versions() {
+            let rules = Rules::load(version).unwrap();
+            assert_eq!(rules.version, version);
+        }
+        let rules = Rules::load(DEFAULT_VERSION).unwrap();
+        assert_eq!(rules.combat.k_melee, 22);
+        assert_eq!(rules.combat.max_fortify_turns(), 3);
+        assert_eq!(rules.combat.max_oos_turns(), 3);
+        assert!(rules.unit("warrior").is_some());
+        assert!(matches!(
+            Rules::load("9.9.9"),
+            Err(RulesError::UnknownVersion(_))
+        ));
+    }
+
+    #[test]
+    fn test_validation_rejects_bad_references() {
+        let mut rules = Rules::load(DEFAULT_VERSION).unwrap().clone();
+        rules
+            .techs
+            .get_mut("pottery")
+            .unwrap()
+            .prereqs
+            .push("time_travel".to_string());
+        assert!(rules.validate().is_err());
+
+        let mut rules = Rules::load(DEFAULT_VERSION).unwrap().clone();
+        rules.terrain.remove(&Terrain::Marsh);
+        assert!(rules.validate().is_err());
+
+        let mut rules = Rules::load(DEFAULT_VERSION).unwrap().clone();
+        rules.units.get_mut("warrior").unwrap().requires_tech = Some("magic".to_string());
+        assert!(rules.validate().is_err());
+
+        let mut rules = Rules::load(DEFAULT_VERSION).unwrap().clone();
+        rules.policy.slots[0].requires_tech = Some("magic".to_string());
+        assert!(rules.validate().is_err());
+    }
+
+    #[test]
+    fn test_validation_rejects_negative_city_growth() {
+        let mut rules = Rules::load(DEFAULT_VERSION).unwrap().clone();
+        rules.combat.city_strength_per_pop = -1;
+        assert!(rules.validate().is_err());
+
+        let mut rules = Rules::load(DEFAULT_VERSION).unwrap().clone();
+        rules.combat.city_wall_repair = -5;
+        assert!(rules.validate().is_err());
+    }
+
+    #[test]
+    fn test_validation_bounds_turn_limits() {
+        let mut rules = Rules::load(DEFAULT_VERSION).unwrap().clone();
+        rules.combat.fortify_cap_pct = -10;
+        assert!(rules.validate().is_err());
+
+        let mut rules = Rules::load(DEFAULT_VERSION).unwrap().clone();
+        rules.combat.oos_cap_pct = 10;
+        assert!(rules.validate().is_err());
+
+        let mut rules = Rules::load(DEFAULT_VERSION).unwrap().clone();
+        rules.combat.fortify_per_turn_pct = 1;
+        rules.combat.fortify_cap_pct = 256;
+        assert!(rules.validate().is_err());
+        rules.combat.fortify_cap_pct = 255;
+        assert!(rules.validate().is_ok());
+        assert_eq!(rules.combat.max_fortify_turns(), 255);
+    }
+}
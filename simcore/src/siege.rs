@@ -0,0 +1,398 @@
+//! City sieges
+//!
+//! Cities defend with their own strength, a base plus a bonus per citizen, behind
+//! walls. Attacks on a city wear down its wall HP. While the walls stand the city keeps
+//! its `city_def_pct` bonus, for itself and for any garrison, and may make one ranged
+//! strike per turn. At zero wall HP the city is breached, and a melee unit moving onto
+//! it captures it together with its queue and districts. Walls are repaired each turn
+//! no enemy combat unit stands next to the city.
+
+use crate::combat::{bank, damage, factor, strength, take_damage, CombatContext};
+use crate::effects::{Event, Recorder};
+use crate::rules::Rules;
+use crate::{
+    policy, tech, validation, zoc, City, CityId, Fixed, PlayerId, SimError, State, Unit, UnitId,
+    ValidationError,
+};
+
+/// Modifiers for `attacker` striking the walls of `city`
+pub fn city_context(
+    state: &State,
+    attacker: &Unit,
+    city: &City,
+) -> Result<CombatContext, SimError> {
+    let rules = state.rules()?;
+    let att_tile = state
+        .map
+        .tile(attacker.pos)
+        .ok_or(ValidationError::OutOfBounds(attacker.pos))?;
+    let city_tile = state
+        .map
+        .tile(city.pos)
+        .ok_or(ValidationError::OutOfBounds(city.pos))?;
+    let flankers = state
+        .map
+        .neighbors(city.pos)
+        .flat_map(|n| state.units_at(n))
+        .filter(|u| {
+            u.id != attacker.id && u.owner == attacker.owner && u.is_combat() && u.is_alive()
+        })
+        .count() as u32;
+    Ok(CombatContext {
+        flankers,
+        across_river: state.map.river_between(attacker.pos, city.pos),
+        high_ground: city_tile.elevation > att_tile.elevation,
+        terrain_def_pct: rules.terrain(city_tile.terrain).defense_pct,
+        in_city: !city.is_breached(&rules.combat),
+        counter_fire: false,
+        attacker_policy_pct: policy::combat_pct(state, rules, attacker.owner),
+        defender_policy_pct: policy::combat_pct(state, rules, city.owner),
+    })
+}
+
+/// `unit` may take `city` by moving onto it
+pub fn can_capture(rules: &Rules, unit: &Unit, city: &City) -> bool {
+    let melee = rules.unit(&unit.kind).is_some_and(|d| !d.is_ranged());
+    unit.owner != city.owner && unit.is_combat() && melee && city.is_breached(&rules.combat)
+}
+
+pub(crate) fn validate_attack_city(
+    state: &State,
+    player: PlayerId,
+    attacker: UnitId,
+    city: CityId,
+) -> Result<(), SimError> {
+    let att = state
+        .unit(attacker)
+        .ok_or(ValidationError::UnitNotFound(attacker))?;
+    validation::check_owner(player, att.owner)?;
+    let target = state
+        .city(city)
+        .ok_or(ValidationError::CityNotFound(city))?;
+    if target.owner == att.owner {
+        return Err(ValidationError::CityNotHostile(city).into());
+    }
+    let rules = state.rules()?;
+    let kind = rules
+        .unit(&att.kind)
+        .filter(|d| att.is_combat() && d.strength > 0)
+        .ok_or(ValidationError::CannotAttack(attacker))?;
+    if target.is_breached(&rules.combat) {
+        return Err(ValidationError::WallsBreached(city).into());
+    }
+    if att.moves_left < 1 {
+        return Err(ValidationError::InsufficientAP {
+            needed: 1,
+            available: att.moves_left,
+        }
+        .into());
+    }
+    let range = kind.range.max(1);
+    let distance = att.pos.distance(target.pos);
+    if distance > range {
+        return Err(ValidationError::OutOfRange { distance, range }.into());
+    }
+    Ok(())
+}
+
+/// Resolve an already validated attack on a city's walls. Melee attackers take the
+/// city's return blow; ranged attackers do not.
+pub(crate) fn apply_attack_city(
+    state: &mut State,
+    attacker: UnitId,
+    id: CityId,
+    rec: &mut Recorder,
+) -> Result<(), SimError> {
+    let rules = state.rules()?;
+    let c = &rules.combat;
+    let (Some(att), Some(city)) = (state.unit(attacker), state.city(id)) else {
+        return Ok(());
+    };
+    let ctx = city_context(state, att, city)?;
+    let def = rules.unit(&att.kind);
+    let ranged = def.is_some_and(|d| d.is_ranged());
+    let (k, base) = if ranged {
+        (c.k_ranged, def.map_or(0, |d| d.ranged_strength))
+    } else {
+        (c.k_melee, strength(rules, att))
+    };
+    let own = Fixed::from_int(base) * factor(&ctx.attack_modifiers(att, c, !ranged));
+    let walls = Fixed::from_int(city.strength(c))
+        * factor(&ctx.cover_modifiers(Fixed::ZERO, Fixed::ZERO, c));
+    let alpha = Fixed::from_pct(c.alpha_pct);
+    let (to_walls, wall_bank) = bank(city.wall_wounds, damage(k, own, walls, alpha));
+    let counter = if ranged {
+        Fixed::ZERO
+    } else {
+        damage(c.k_melee, walls, own, alpha)
+    };
+    let (to_att, att_bank) = bank(att.wound_bank, counter);
+
+    rec.unit(state, attacker);
+    rec.city(state, id);
+    if let Some(att) = state.units.get_mut(&attacker) {
+        att.moves_left = 0;
+        att.break_fortify();
+    }
+    let city = state.cities.get_mut(&id).expect("checked above");
+    city.wall_damage = (city.wall_damage + to_walls).min(c.city_wall_hp);
+    city.wall_wounds = wall_bank;
+    if to_walls > 0 {
+        rec.emit(Event::CityDamaged {
+            city: id,
+            damage: to_walls,
+            wall_hp: city.wall_hp(c),
+        });
+    }
+    if city.is_breached(c) {
+        city.wall_wounds = Fixed::ZERO;
+        rec.emit(Event::CityBreached { city: id });
+    }
+    take_damage(state, attacker, to_att, att_bank, rec);
+    Ok(())
+}
+
+pub(crate) fn validate_strike(
+    state: &State,
+    player: PlayerId,
+    city: CityId,
+    target: UnitId,
+) -> Result<(), SimError> {
+    let striker = state
+        .city(city)
+        .ok_or(ValidationError::CityNotFound(city))?;
+    validation::check_owner(player, striker.owner)?;
+    let unit = state
+        .unit(target)
+        .ok_or(ValidationError::UnitNotFound(target))?;
+    if unit.owner == striker.owner {
+        return Err(ValidationError::NotHostile(target).into());
+    }
+    let c = &state.rules()?.combat;
+    if striker.struck || striker.is_breached(c) {
+        return Err(ValidationError::CityCannotStrike(city).into());
+    }
+    let distance = striker.pos.distance(unit.pos);
+    if distance > c.city_range {
+        return Err(ValidationError::OutOfRange {
+            distance,
+            range: c.city_range,
+        }
+        .into());
+    }
+    Ok(())
+}
+
+/// Resolve an already validated city strike; the target cannot return fire
+pub(crate) fn apply_strike(
+    state: &mut State,
+    id: CityId,
+    target: UnitId,
+    rec: &mut Recorder,
+) -> Result<(), SimError> {
+    let rules = state.rules()?;
+    let c = &rules.combat;
+    let (Some(city), Some(unit)) = (state.city(id), state.unit(target)) else {
+        return Ok(());
+    };
+    let city_tile = state
+        .map
+        .tile(city.pos)
+        .ok_or(ValidationError::OutOfBounds(city.pos))?;
+    let unit_tile = state
+        .map
+        .tile(unit.pos)
+        .ok_or(ValidationError::OutOfBounds(unit.pos))?;
+    let ctx = CombatContext {
+        high_ground: unit_tile.elevation > city_tile.elevation,
+        terrain_def_pct: rules.terrain(unit_tile.terrain).defense_pct,
+        in_city: state
+            .city_at(unit.pos)
+            .is_some_and(|other| !other.is_breached(c)),
+        attacker_policy_pct: policy::combat_pct(state, rules, city.owner),
+        defender_policy_pct: policy::combat_pct(state, rules, unit.owner),
+        ..Default::default()
+    };
+    let own =
+        Fixed::from_int(city.strength(c)) * (Fixed::ONE + Fixed::from_pct(ctx.attacker_policy_pct));
+    let sd = Fixed::from_int(strength(rules, unit)) * factor(&ctx.defense_modifiers(unit, c));
+    let hit = damage(c.k_ranged, own, sd, Fixed::from_pct(c.alpha_pct));
+    let (to_unit, unit_bank) = bank(unit.wound_bank, hit);
+
+    rec.city(state, id);
+    rec.unit(state, target);
+    state.cities.get_mut(&id).expect("checked above").struck = true;
+    rec.emit(Event::CityStruck { city: id, target });
+    take_damage(state, target, to_unit, unit_bank, rec);
+    Ok(())
+}
+
+/// Hand `id` to `owner`; the queue and districts go with it
+pub(crate) fn capture(
+    state: &mut State,
+    id: CityId,
+    owner: PlayerId,
+    rec: &mut Recorder,
+) -> Result<(), SimError> {
+    rec.city(state, id);
+    let Some(city) = state.cities.get_mut(&id) else {
+        return Ok(());
+    };
+    let from = city.owner;
+    city.owner = owner;
+    city.struck = false;
+    rec.emit(Event::CityCaptured {
+        city: id,
+        from,
+        to: owner,
+    });
+    tech::freeze_lost_districts(state, id, from, rec)
+}
+
+/// Upkeep: ready city strikes and repair walls of cities not under siege
+pub(crate) fn repair(state: &mut State) -> Result<(), SimError> {
+    let c = &state.rules()?.combat;
+    let besieged: Vec<(CityId, bool)> = state
+        .cities
+        .values()
+        .map(|city| (city.id, zoc::is_controlled(state, city.owner, city.pos)))
+        .collect();
+    for (id, besieged) in besieged {
+        let city = state.cities.get_mut(&id).expect("id collected above");
+        city.struck = false;
+        if !besieged {
+            city.wall_damage = (city.wall_damage - c.city_wall_repair).max(0);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{code, new_state};
+    use crate::{apply_action, end_turn, validate_action, Action, TileCoord};
+
+    fn setup() -> (State, CityId) {
+        let mut state = new_state(10, 10);
+        state.add_player(PlayerId(1), "Khiamians");
+        let city = state
+            .found_city(PlayerId(1), "Target", TileCoord::new(4, 4))
+            .unwrap();
+        state
+            .cities
+            .get_mut(&city)
+            .unwrap()
+            .queue
+            .push(crate::ProductionOrder {
+                item: crate::ProductionItem::Unit {
+                    kind: "warrior".to_string(),
+                },
+                cost: 40,
+            });
+        (state, city)
+    }
+
+    #[test]
+    fn test_walls_fall_then_melee_captures() {
+        let (mut state, city) = setup();
+        let mut attackers = Vec::new();
+        for pos in [(5, 4), (3, 4), (4, 3), (3, 3), (4, 5), (3, 5)] {
+            attackers.push(
+                state
+                    .spawn_unit(PlayerId(0), "spearman", TileCoord::new(pos.0, pos.1))
+                    .unwrap(),
+            );
+        }
+        let c = &state.rules().unwrap().combat;
+        let wall_hp = |state: &State| state.city(city).unwrap().wall_hp(c);
+
+        // Walls stand: no entry
+        let enter = |unit| Action::MoveUnit {
+            unit,
+            path: vec![TileCoord::new(4, 4)],
+            ap: 1,
+        };
+        assert_eq!(code(&state, &enter(attackers[0])), Some(20));
+
+        let mut events = Vec::new();
+        for &attacker in &attackers {
+            if state.city(city).unwrap().is_breached(c) {
+                break;
+            }
+            let effects = apply_action(
+                &mut state,
+                PlayerId(0),
+                Action::AttackCity { attacker, city },
+            )
+            .unwrap();
+            events.extend(effects.events);
+        }
+        assert_eq!(wall_hp(&state), 0);
+        assert!(events
+            .iter()
+            .any(|e| matches!(e, Event::CityBreached { .. })));
+        assert_eq!(
+            code(
+                &state,
+                &Action::AttackCity {
+                    attacker: attackers[5],
+                    city
+                }
+            ),
+            Some(27)
+        );
+
+        // Breached: the last fresh spearman walks in and takes the city
+        let effects = apply_action(&mut state, PlayerId(0), enter(attackers[5])).unwrap();
+        let taken = state.city(city).unwrap();
+        assert_eq!(taken.owner, PlayerId(0));
+        assert_eq!(taken.queue.len(), 1);
+        assert!(effects.events.contains(&Event::CityCaptured {
+            city,
+            from: PlayerId(1),
+            to: PlayerId(0),
+        }));
+    }
+
+    #[test]
+    fn test_siege_blocks_wall_repair() {
+        let (mut state, city) = setup();
+        state.cities.get_mut(&city).unwrap().wall_damage = 50;
+        let unit = state
+            .spawn_unit(PlayerId(0), "warrior", TileCoord::new(5, 4))
+            .unwrap();
+        end_turn(&mut state).unwrap();
+        assert_eq!(state.city(city).unwrap().wall_damage, 50);
+
+        state.units.remove(&unit);
+        end_turn(&mut state).unwrap();
+        assert_eq!(state.city(city).unwrap().wall_damage, 40);
+    }
+
+    #[test]
+    fn test_city_strikes_once_per_turn_in_range() {
+        let (mut state, city) = setup();
+        let near = state
+            .spawn_unit(PlayerId(0), "warrior", TileCoord::new(6, 4))
+            .unwrap();
+        let far = state
+            .spawn_unit(PlayerId(0), "warrior", TileCoord::new(8, 4))
+            .unwrap();
+        let strike = |target| Action::CityStrike { city, target };
+        let by_owner = |state: &State, action: &Action| {
+            validate_action(state, PlayerId(1), action).map_err(|e| e.code())
+        };
+        assert_eq!(by_owner(&state, &strike(far)), Err(Some(24)));
+        // Only the city's owner can order the strike
+        assert_eq!(code(&state, &strike(near)), Some(4));
+
+        let effects = apply_action(&mut state, PlayerId(1), strike(near)).unwrap();
+        assert!(state.unit(near).unwrap().hp < 100);
+        assert_eq!(effects.events[0], Event::CityStruck { city, target: near });
+        assert_eq!(by_owner(&state, &strike(near)), Err(Some(28)));
+
+        end_turn(&mut state).unwrap();
+        assert!(by_owner(&state, &strike(near)).is_ok());
+    }
+}
@@ -0,0 +1,141 @@
+//! Territory and supply lines
+//!
+//! A player's territory is every tile within `territory_radius` of one of their cities,
+//! plus their district tiles. Supply flows out of territory for up to `supply_range`
+//! steps over passable land, and cannot pass through tiles held by another player's
+//! unit or city. Each upkeep, units inside the network recover one out-of-supply turn
+//! and units outside it accrue one, up to the point the combat penalty stops growing.
+
+use crate::{PlayerId, SimError, State, TileCoord};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Tiles `player` owns through their cities and districts
+pub fn territory(state: &State, player: PlayerId) -> Result<BTreeSet<TileCoord>, SimError> {
+    let radius = state.rules()?.supply.territory_radius;
+    let mut tiles = BTreeSet::new();
+    for city in state.cities.values().filter(|c| c.owner == player) {
+        tiles.extend(state.map.tiles_within(city.pos, radius));
+        tiles.extend(city.districts.iter().map(|d| d.tile));
+    }
+    Ok(tiles)
+}
+
+/// Tiles where `player`'s units are in supply
+pub fn supply_overlay(state: &State, player: PlayerId) -> Result<BTreeSet<TileCoord>, SimError> {
+    let range = state.rules()?.supply.supply_range;
+    let hostile = |c: TileCoord| {
+        state.units_at(c).any(|u| u.owner != player)
+            || state.city_at(c).is_some_and(|city| city.owner != player)
+    };
+
+    // Breadth-first out of territory, one ring per step of range
+    let mut supplied: BTreeSet<TileCoord> = territory(state, player)?
+        .into_iter()
+        .filter(|&c| !hostile(c))
+        .collect();
+    let mut frontier = supplied.clone();
+    for _ in 0..range {
+        let mut next = BTreeSet::new();
+        for &from in &frontier {
+            for to in state.map.neighbors(from) {
+                let land = state
+                    .map
+                    .tile(to)
+                    .is_some_and(|t| t.is_passable() && !t.terrain.is_water());
+                if land && !hostile(to) && !supplied.contains(&to) {
+                    next.insert(to);
+                }
+            }
+        }
+        supplied.extend(next.iter().copied());
+        frontier = next;
+    }
+    Ok(supplied)
+}
+
+/// Upkeep: move every unit's out-of-supply counter one step toward its new status
+pub(crate) fn update(state: &mut State) -> Result<(), SimError> {
+    let max_turns = state.rules()?.combat.max_oos_turns();
+    let owners: BTreeSet<PlayerId> = state.units.values().map(|u| u.owner).collect();
+    let mut overlays = BTreeMap::new();
+    for owner in owners {
+        overlays.insert(owner, supply_overlay(state, owner)?);
+    }
+    for unit in state.units.values_mut() {
+        if overlays[&unit.owner].contains(&unit.pos) {
+            unit.out_of_supply_turns = unit.out_of_supply_turns.saturating_sub(1);
+        } else {
+            unit.out_of_supply_turns = unit.out_of_supply_turns.saturating_add(1).min(max_turns);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{end_turn, Map, Terrain};
+
+    #[test]
+    fn test_supply_reaches_past_territory() {
+        let mut state = State::new();
+        state.map = Map::new(16, 8).unwrap();
+        state
+            .found_city(PlayerId(0), "Home", TileCoord::new(2, 4))
+            .unwrap();
+        let land = territory(&state, PlayerId(0)).unwrap();
+        assert!(land.contains(&TileCoord::new(4, 4)));
+        assert!(!land.contains(&TileCoord::new(5, 4)));
+
+        let supplied = supply_overlay(&state, PlayerId(0)).unwrap();
+        assert!(supplied.contains(&TileCoord::new(7, 4)));
+        assert!(!supplied.contains(&TileCoord::new(8, 4)));
+        assert!(supply_overlay(&state, PlayerId(1)).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_enemies_and_water_cut_supply() {
+        let mut state = State::new();
+        state.map = Map::new(16, 8).unwrap();
+        state
+            .found_city(PlayerId(0), "Home", TileCoord::new(2, 4))
+            .unwrap();
+        for y in 0..8 {
+            state
+                .map
+                .set_terrain(TileCoord::new(5, y), Terrain::Ocean)
+                .unwrap();
+        }
+        state
+            .spawn_unit(PlayerId(1), "warrior", TileCoord::new(3, 2))
+            .unwrap();
+        let supplied = supply_overlay(&state, PlayerId(0)).unwrap();
+        assert!(!supplied.contains(&TileCoord::new(6, 4)));
+        assert!(!supplied.contains(&TileCoord::new(3, 2)));
+        assert!(supplied.contains(&TileCoord::new(3, 3)));
+    }
+
+    #[test]
+    fn test_counters_grow_outside_and_decay_inside() {
+        let mut state = State::new();
+        state.map = Map::new(20, 8).unwrap();
+        state
+            .found_city(PlayerId(0), "Home", TileCoord::new(2, 4))
+            .unwrap();
+        let home = state
+            .spawn_unit(PlayerId(0), "warrior", TileCoord::new(3, 4))
+            .unwrap();
+        let far = state
+            .spawn_unit(PlayerId(0), "warrior", TileCoord::new(18, 4))
+            .unwrap();
+        for _ in 0..5 {
+            end_turn(&mut state).unwrap();
+        }
+        assert_eq!(state.unit(home).unwrap().out_of_supply_turns, 0);
+        assert_eq!(state.unit(far).unwrap().out_of_supply_turns, 3);
+
+        state.units.get_mut(&far).unwrap().pos = TileCoord::new(4, 4);
+        end_turn(&mut state).unwrap();
+        assert_eq!(state.unit(far).unwrap().out_of_supply_turns, 2);
+    }
+}
@@ -0,0 +1,485 @@
+//! Technology tree and research
+//!
+//! The tree is the rules' tech table: a tech becomes available once every one of its
+//! prerequisites is known. Each player researches at most one tech at a time. Science
+//! earned at end of turn is banked against that tech, and progress on a tech is kept
+//! if the player switches away and comes back later. Science earned while nothing is
+//! being researched, and any overflow past a tech's cost, is lost.
+//!
+//! A tech can be frozen for a player by a climate shock, sanctions or the loss of the
+//! districts built on it. A frozen tech stays known but stops counting: it unlocks
+//! nothing, satisfies no prerequisite and cannot be researched. Timed freezes thaw in
+//! upkeep once their last turn has passed; a lost-district freeze thaws once the player
+//! owns a district of that kind again.
+
+use crate::effects::{Event, Recorder};
+use crate::rules::Rules;
+use crate::{CityId, Fixed, Player, PlayerId, SimError, State, ValidationError};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Why a tech was frozen
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FreezeCause {
+    ClimateShock,
+    Sanctions,
+    /// The player lost their last district of this kind
+    LostDistrict(String),
+}
+
+/// A suspended tech
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Freeze {
+    pub cause: FreezeCause,
+    /// Last turn the freeze holds; `None` until it is lifted
+    pub until: Option<i32>,
+}
+
+/// `player` knows `id` and it is not frozen
+pub fn has(player: &Player, id: &str) -> bool {
+    player.known_techs.contains(id) && !player.frozen_techs.contains_key(id)
+}
+
+/// `player` may start researching `id`
+pub fn is_available(rules: &Rules, player: &Player, id: &str) -> bool {
+    let Some(def) = rules.tech(id) else {
+        return false;
+    };
+    !player.known_techs.contains(id)
+        && !player.frozen_techs.contains_key(id)
+        && def.prereqs.iter().all(|p| has(player, p))
+}
+
+/// Techs `player` may start researching, in id order
+pub fn available(rules: &Rules, player: &Player) -> Vec<String> {
+    rules
+        .techs
+        .keys()
+        .filter(|id| is_available(rules, player, id))
+        .cloned()
+        .collect()
+}
+
+/// Science still needed to finish `id`, or `None` if the tech does not exist
+pub fn remaining(rules: &Rules, player: &Player, id: &str) -> Option<Fixed> {
+    let cost = Fixed::from_int(rules.tech(id)?.cost);
+    let banked = player
+        .research_progress
+        .get(id)
+        .copied()
+        .unwrap_or(Fixed::ZERO);
+    Some((cost - banked).max(Fixed::ZERO))
+}
+
+pub(crate) fn validate_choose(state: &State, player: PlayerId, id: &str) -> Result<(), SimError> {
+    let player = state
+        .player(player)
+        .ok_or(ValidationError::PlayerNotFound(player))?;
+    if player.frozen_techs.contains_key(id) {
+        return Err(ValidationError::TechFrozen(id.to_string()).into());
+    }
+    if !is_available(state.rules()?, player, id) {
+        return Err(ValidationError::TechNotAvailable(id.to_string()).into());
+    }
+    Ok(())
+}
+
+/// Reject a build gated on `requires` unless `owner` has it
+///
+/// Owners who are not registered players are not gated; they cannot act, so this only
+/// shows in queries such as `district::placements`.
+pub(crate) fn check_unlocked(
+    state: &State,
+    owner: PlayerId,
+    requires: Option<&str>,
+) -> Result<(), ValidationError> {
+    let (Some(tech), Some(player)) = (requires, state.player(owner)) else {
+        return Ok(());
+    };
+    if player.frozen_techs.contains_key(tech) {
+        return Err(ValidationError::TechFrozen(tech.to_string()));
+    }
+    if !player.known_techs.contains(tech) {
+        return Err(ValidationError::TechNotAvailable(tech.to_string()));
+    }
+    Ok(())
+}
+
+/// Point `player`'s research at an already validated tech
+pub(crate) fn apply_choose(state: &mut State, player: PlayerId, id: String, rec: &mut Recorder) {
+    rec.player(state, player);
+    let Some(p) = state.players.get_mut(&player) else {
+        return;
+    };
+    if p.researching.as_deref() == Some(id.as_str()) {
+        return;
+    }
+    p.researching = Some(id.clone());
+    rec.emit(Event::ResearchStarted { player, tech: id });
+}
+
+/// End of turn: bank each player's science and complete any tech that is paid for
+pub(crate) fn research(
+    state: &mut State,
+    science: &BTreeMap<PlayerId, Fixed>,
+    rec: &mut Recorder,
+) -> Result<(), SimError> {
+    let rules = state.rules()?;
+    for (&id, &amount) in science {
+        if !state.players.contains_key(&id) {
+            continue;
+        }
+        rec.player(state, id);
+        let player = state.players.get_mut(&id).expect("checked above");
+        let Some(tech) = player.researching.clone() else {
+            continue;
+        };
+        if player.frozen_techs.contains_key(&tech) {
+            continue;
+        }
+        let Some(def) = rules.tech(&tech) else {
+            continue;
+        };
+        let banked = player
+            .research_progress
+            .entry(tech.clone())
+            .or_insert(Fixed::ZERO);
+        *banked += amount;
+        if *banked < Fixed::from_int(def.cost) {
+            continue;
+        }
+        player.research_progress.remove(&tech);
+        player.researching = None;
+        player.known_techs.insert(tech.clone());
+        rec.emit(Event::TechResearched { player: id, tech });
+    }
+    Ok(())
+}
+
+/// Freeze `tech` for `player`; a tech that is already frozen keeps its first freeze
+pub(crate) fn freeze(
+    state: &mut State,
+    player: PlayerId,
+    tech: &str,
+    freeze: Freeze,
+    rec: &mut Recorder,
+) {
+    rec.player(state, player);
+    let Some(p) = state.players.get_mut(&player) else {
+        return;
+    };
+    if p.frozen_techs.contains_key(tech) {
+        return;
+    }
+    let cause = freeze.cause.clone();
+    p.frozen_techs.insert(tech.to_string(), freeze);
+    rec.emit(Event::TechFrozen {
+        player,
+        tech: tech.to_string(),
+        cause,
+    });
+}
+
+/// Lift any freeze on `tech` for `player`
+pub(crate) fn unfreeze(state: &mut State, player: PlayerId, tech: &str, rec: &mut Recorder) {
+    rec.player(state, player);
+    let Some(p) = state.players.get_mut(&player) else {
+        return;
+    };
+    if p.frozen_techs.remove(tech).is_some() {
+        rec.emit(Event::TechUnfrozen {
+            player,
+            tech: tech.to_string(),
+        });
+    }
+}
+
+fn owns_district(state: &State, player: PlayerId, kind: &str) -> bool {
+    state
+        .cities
+        .values()
+        .filter(|c| c.owner == player)
+        .any(|c| c.districts.iter().any(|d| d.kind == kind))
+}
+
+/// After `city` changed hands, freeze the techs behind district kinds its old owner
+/// no longer has anywhere
+pub(crate) fn freeze_lost_districts(
+    state: &mut State,
+    city: CityId,
+    from: PlayerId,
+    rec: &mut Recorder,
+) -> Result<(), SimError> {
+    let rules = state.rules()?;
+    let Some(kinds) = state.city(city).map(|c| {
+        c.districts
+            .iter()
+            .map(|d| d.kind.clone())
+            .collect::<Vec<_>>()
+    }) else {
+        return Ok(());
+    };
+    for kind in kinds {
+        let Some(tech) = rules.district(&kind).and_then(|d| d.requires_tech.clone()) else {
+            continue;
+        };
+        let known = state
+            .player(from)
+            .is_some_and(|p| p.known_techs.contains(&tech));
+        if known && !owns_district(state, from, &kind) {
+            let lost = Freeze {
+                cause: FreezeCause::LostDistrict(kind),
+                until: None,
+            };
+            freeze(state, from, &tech, lost, rec);
+        }
+    }
+    Ok(())
+}
+
+/// Upkeep: lift freezes that have run out or whose district is back
+pub(crate) fn thaw(state: &mut State, rec: &mut Recorder) {
+    let mut thawed = Vec::new();
+    for player in state.players.values() {
+        for (tech, freeze) in &player.frozen_techs {
+            let done = match (&freeze.cause, freeze.until) {
+                (_, Some(until)) => state.turn >= until,
+                (FreezeCause::LostDistrict(kind), None) => owns_district(state, player.id, kind),
+                (_, None) => false,
+            };
+            if done {
+                thawed.push((player.id, tech.clone()));
+            }
+        }
+    }
+    for (player, tech) in thawed {
+        unfreeze(state, player, &tech, rec);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::new_state;
+    use crate::{
+        apply_action, end_turn_with_effects, enumerate_legal_actions, validate_action, Action,
+        TileCoord,
+    };
+
+    fn setup() -> State {
+        new_state(10, 10)
+    }
+
+    fn choose(id: &str) -> Action {
+        Action::ChooseTech { id: id.into() }
+    }
+
+    #[test]
+    fn test_only_available_techs_can_be_chosen() {
+        let state = setup();
+        let rules = state.rules().unwrap();
+        let open = available(rules, state.player(PlayerId(0)).unwrap());
+        assert!(open.contains(&"foraging".to_string()));
+        assert!(!open.contains(&"writing".to_string()));
+
+        let err = validate_action(&state, PlayerId(0), &choose("writing")).unwrap_err();
+        assert_eq!(err.code(), Some(10));
+        let err = validate_action(&state, PlayerId(0), &choose("steam_power")).unwrap_err();
+        assert_eq!(err.code(), Some(10));
+        let err = validate_action(&state, PlayerId(3), &choose("foraging")).unwrap_err();
+        assert_eq!(err.code(), Some(3));
+    }
+
+    #[test]
+    fn test_choices_follow_the_acting_player() {
+        let mut state = setup();
+        state.add_player(PlayerId(1), "Bo");
+        learn(&mut state, &["foraging"]);
+        for player in [PlayerId(0), PlayerId(1)] {
+            let choices: Vec<Action> = enumerate_legal_actions(&state, player)
+                .into_iter()
+                .filter(|a| matches!(a, Action::ChooseTech { .. }))
+                .collect();
+            assert!(!choices.is_empty());
+            for action in &choices {
+                assert!(validate_action(&state, player, action).is_ok());
+            }
+        }
+        // Only player 0 knows foraging, so only they can move on to mining
+        assert!(validate_action(&state, PlayerId(0), &choose("mining")).is_ok());
+        let err = validate_action(&state, PlayerId(1), &choose("mining")).unwrap_err();
+        assert_eq!(err.code(), Some(10));
+
+        apply_action(&mut state, PlayerId(1), choose("foraging")).unwrap();
+        assert_eq!(state.players[&PlayerId(0)].researching, None);
+        assert_eq!(
+            state.players[&PlayerId(1)].researching.as_deref(),
+            Some("foraging")
+        );
+    }
+
+    #[test]
+    fn test_science_completes_research() {
+        let mut state = setup();
+        state
+            .found_city(PlayerId(0), "Home", TileCoord::new(4, 4))
+            .unwrap();
+        let effects = apply_action(&mut state, PlayerId(0), choose("foraging")).unwrap();
+        assert_eq!(effects.events[0].kind(), "ResearchStarted");
+
+        let cost = state.rules().unwrap().tech("foraging").unwrap().cost;
+        let mut done = false;
+        for _ in 0..cost {
+            let effects = end_turn_with_effects(&mut state).unwrap();
+            if effects.events.iter().any(|e| e.kind() == "TechResearched") {
+                done = true;
+                break;
+            }
+        }
+        assert!(done);
+        let player = state.player(PlayerId(0)).unwrap();
+        assert!(player.known_techs.contains("foraging"));
+        assert_eq!(player.researching, None);
+        assert!(validate_action(&state, PlayerId(0), &choose("foraging")).is_err());
+    }
+
+    #[test]
+    fn test_switching_keeps_partial_progress() {
+        let mut state = setup();
+        state
+            .players
+            .get_mut(&PlayerId(0))
+            .unwrap()
+            .known_techs
+            .insert("foraging".into());
+        let mut science = BTreeMap::new();
+        science.insert(PlayerId(0), Fixed::from_int(3));
+        let mut rec = Recorder::begin(&state);
+
+        apply_choose(&mut state, PlayerId(0), "mining".into(), &mut rec);
+        research(&mut state, &science, &mut rec).unwrap();
+        apply_choose(&mut state, PlayerId(0), "pottery".into(), &mut rec);
+        research(&mut state, &science, &mut rec).unwrap();
+
+        let rules = state.rules().unwrap();
+        let player = state.player(PlayerId(0)).unwrap();
+        let mining = Fixed::from_int(rules.tech("mining").unwrap().cost);
+        assert_eq!(
+            remaining(rules, player, "mining"),
+            Some(mining - Fixed::from_int(3))
+        );
+        assert_eq!(
+            player.research_progress.get("pottery"),
+            Some(&Fixed::from_int(3))
+        );
+    }
+
+    fn learn(state: &mut State, techs: &[&str]) {
+        let player = state.players.get_mut(&PlayerId(0)).unwrap();
+        player
+            .known_techs
+            .extend(techs.iter().map(|t| t.to_string()));
+    }
+
+    #[test]
+    fn test_frozen_tech_blocks_builds_and_research_until_thawed() {
+        let mut state = setup();
+        learn(&mut state, &["foraging", "animal_husbandry"]);
+        let city = state
+            .found_city(PlayerId(0), "Home", TileCoord::new(4, 4))
+            .unwrap();
+        let horseman = Action::BuildUnit {
+            city,
+            kind: "horseman".into(),
+        };
+        assert!(validate_action(&state, PlayerId(0), &horseman).is_ok());
+        assert!(validate_action(&state, PlayerId(0), &choose("archery")).is_ok());
+
+        let effects = crate::freeze_tech(
+            &mut state,
+            PlayerId(0),
+            "animal_husbandry",
+            FreezeCause::Sanctions,
+            Some(2),
+        )
+        .unwrap();
+        assert_eq!(effects.events[0].kind(), "TechFrozen");
+        assert_eq!(
+            validate_action(&state, PlayerId(0), &horseman)
+                .unwrap_err()
+                .code(),
+            Some(31)
+        );
+        let err = validate_action(&state, PlayerId(0), &choose("archery")).unwrap_err();
+        assert_eq!(err.code(), Some(10));
+        let err = validate_action(&state, PlayerId(0), &choose("animal_husbandry")).unwrap_err();
+        assert_eq!(err.code(), Some(31));
+
+        end_turn_with_effects(&mut state).unwrap();
+        assert!(validate_action(&state, PlayerId(0), &horseman).is_err());
+        let effects = end_turn_with_effects(&mut state).unwrap();
+        assert!(effects.events.iter().any(|e| e.kind() == "TechUnfrozen"));
+        assert!(validate_action(&state, PlayerId(0), &horseman).is_ok());
+
+        // Owners outside the roster cannot order anything, not even their own cities
+        let other = state
+            .found_city(PlayerId(5), "Elsewhere", TileCoord::new(8, 8))
+            .unwrap();
+        let build = Action::BuildUnit {
+            city: other,
+            kind: "swordsman".into(),
+        };
+        let err = validate_action(&state, PlayerId(5), &build).unwrap_err();
+        assert_eq!(err.code(), Some(3));
+    }
+
+    #[test]
+    fn test_losing_last_district_freezes_its_tech() {
+        let mut state = setup();
+        state.add_player(PlayerId(1), "Bo");
+        learn(&mut state, &["foraging", "mining"]);
+        let lost = state
+            .found_city(PlayerId(0), "Mine", TileCoord::new(2, 2))
+            .unwrap();
+        state
+            .cities
+            .get_mut(&lost)
+            .unwrap()
+            .districts
+            .push(crate::District {
+                kind: "industrial_zone".into(),
+                tile: TileCoord::new(3, 2),
+            });
+
+        let mut rec = Recorder::begin(&state);
+        crate::siege::capture(&mut state, lost, PlayerId(1), &mut rec).unwrap();
+        let frozen = &state.player(PlayerId(0)).unwrap().frozen_techs;
+        assert_eq!(
+            frozen.get("mining").map(|f| &f.cause),
+            Some(&FreezeCause::LostDistrict("industrial_zone".into()))
+        );
+        assert!(!has(state.player(PlayerId(0)).unwrap(), "mining"));
+
+        end_turn_with_effects(&mut state).unwrap();
+        assert!(state
+            .player(PlayerId(0))
+            .unwrap()
+            .frozen_techs
+            .contains_key("mining"));
+
+        let regained = state
+            .found_city(PlayerId(0), "Again", TileCoord::new(7, 7))
+            .unwrap();
+        state
+            .cities
+            .get_mut(&regained)
+            .unwrap()
+            .districts
+            .push(crate::District {
+                kind: "industrial_zone".into(),
+                tile: TileCoord::new(8, 7),
+            });
+        end_turn_with_effects(&mut state).unwrap();
+        assert!(has(state.player(PlayerId(0)).unwrap(), "mining"));
+    }
+}
@@ -0,0 +1,118 @@
+//! Inter-turn pipeline
+//!
+//! Systems run in the canonical contract order: Upkeep → Yields → Events → AI Think →
+//! Digest. Each stage iterates entities in id order so the pipeline is deterministic.
+//! The caller's `Recorder` has already snapshotted every entity; stages only emit events.
+
+use crate::city::{District, ProductionItem};
+use crate::effects::{Event, Recorder};
+use crate::{
+    policy, siege, supply, tech, unit, yields, CityId, Fixed, PlayerId, SimError, State,
+    ValidationError,
+};
+use std::collections::BTreeMap;
+
+/// Run every inter-turn stage and advance the turn counter
+pub(crate) fn run(state: &mut State, rec: &mut Recorder) -> Result<(), SimError> {
+    upkeep(state, rec)?;
+    yields(state, rec)?;
+    rec.emit(Event::TurnEnded { turn: state.turn });
+    state.turn += 1;
+    Ok(())
+}
+
+/// Restore unit action points, dig in fortified units, update supply, ready cities for
+/// the coming turn, thaw frozen techs and reopen policy slots
+fn upkeep(state: &mut State, rec: &mut Recorder) -> Result<(), SimError> {
+    for unit in state.units.values_mut() {
+        unit.moves_left = unit.max_moves;
+    }
+    unit::accrue_fortify(state)?;
+    supply::update(state)?;
+    siege::repair(state)?;
+    tech::thaw(state, rec);
+    policy::expire_cooldowns(state);
+    Ok(())
+}
+
+/// Grow cities, advance their production queues, fill treasuries and put science into
+/// research, all from the yields engine's totals for the turn
+fn yields(state: &mut State, rec: &mut Recorder) -> Result<(), SimError> {
+    let report = yields::compute(state)?;
+    for (&id, output) in &report.cities {
+        let city = state
+            .cities
+            .get_mut(&id)
+            .expect("report covers existing cities");
+        city.store_food(output.yields.total.food);
+        if city.food_stored >= city.food_cap() {
+            city.population += 1;
+            city.food_stored = Fixed::ZERO;
+            rec.emit(Event::CityGrew {
+                city: id,
+                population: city.population,
+            });
+        }
+        city.store_production(output.yields.total.production);
+        complete_production(state, id, rec)?;
+    }
+    let mut science: BTreeMap<PlayerId, Fixed> = BTreeMap::new();
+    for (&owner, output) in &report.players {
+        science.insert(owner, output.total.science);
+        if let Some(player) = state.players.get_mut(&owner) {
+            player.gold += output.total.gold;
+            player.culture += output.total.culture;
+            player.influence += output.total.influence;
+        }
+    }
+    tech::research(state, &science, rec)
+}
+
+/// Finish the head of a city's queue if enough production is banked
+fn complete_production(state: &mut State, id: CityId, rec: &mut Recorder) -> Result<(), SimError> {
+    let city = &state.cities[&id];
+    let Some(order) = city.queue.first().cloned() else {
+        return Ok(());
+    };
+    if city.production_stored < Fixed::from_int(order.cost) {
+        return Ok(());
+    }
+    let (owner, pos) = (city.owner, city.pos);
+
+    match &order.item {
+        ProductionItem::Unit { kind } => {
+            let class = state
+                .rules()?
+                .unit(kind)
+                .ok_or_else(|| ValidationError::UnknownUnitKind(kind.clone()))?
+                .class;
+            if state.unit_of_class_at(pos, class).is_some() {
+                // City tile is occupied; hold the finished unit until it clears
+                return Ok(());
+            }
+            let unit = state.spawn_unit(owner, kind, pos)?;
+            rec.emit(Event::UnitCreated {
+                unit,
+                owner,
+                kind: kind.clone(),
+                pos,
+            });
+        }
+        ProductionItem::District { kind, tile } => {
+            let city = state.cities.get_mut(&id).expect("city exists");
+            city.districts.push(District {
+                kind: kind.clone(),
+                tile: *tile,
+            });
+        }
+    }
+
+    let city = state.cities.get_mut(&id).expect("city exists");
+    city.production_stored -= Fixed::from_int(order.cost);
+    city.queue.remove(0);
+    rec.emit(Event::ProductionCompleted {
+        city: id,
+        item: order.item,
+    });
+    Ok(())
+}
@@ -0,0 +1,217 @@
+//! Unit entities
+
+use crate::effects::{Event, Recorder};
+use crate::rules::CombatConstants;
+use crate::{validation, Fixed, PlayerId, SimError, State, TileCoord, UnitId, ValidationError};
+use serde::{Deserialize, Serialize};
+
+/// Full health for every unit
+pub const MAX_HP: i32 = 100;
+
+/// Stacking class for 1UPT: one combat and one civilian unit may share a tile
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum UnitClass {
+    Combat,
+    Civilian,
+}
+
+/// A unit on the map
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Unit {
+    pub id: UnitId,
+    pub owner: PlayerId,
+    pub kind: String,
+    pub class: UnitClass,
+    pub pos: TileCoord,
+    pub hp: i32,
+    /// Movement/action points left this turn
+    pub moves_left: i32,
+    /// Movement/action points restored at the start of each turn
+    pub max_moves: i32,
+    /// Dug in; the bonus grows each upkeep until the unit moves or attacks
+    pub fortified: bool,
+    /// Upkeeps spent fortified, capped where the bonus stops growing
+    pub fortify_turns: u8,
+    pub out_of_supply_turns: u8,
+    /// Fractional damage banked until it reaches a whole HP
+    pub wound_bank: Fixed,
+}
+
+impl Unit {
+    pub fn new(
+        id: UnitId,
+        owner: PlayerId,
+        kind: impl Into<String>,
+        class: UnitClass,
+        pos: TileCoord,
+        max_moves: i32,
+    ) -> Self {
+        Self {
+            id,
+            owner,
+            kind: kind.into(),
+            class,
+            pos,
+            hp: MAX_HP,
+            moves_left: max_moves,
+            max_moves,
+            fortified: false,
+            fortify_turns: 0,
+            out_of_supply_turns: 0,
+            wound_bank: Fixed::ZERO,
+        }
+    }
+
+    pub fn is_combat(&self) -> bool {
+        self.class == UnitClass::Combat
+    }
+
+    pub fn is_alive(&self) -> bool {
+        self.hp > 0
+    }
+
+    /// Leave fortification and lose the accrued bonus
+    pub fn break_fortify(&mut self) {
+        self.fortified = false;
+        self.fortify_turns = 0;
+    }
+
+    /// Defensive bonus from fortification, in whole percent
+    pub fn fortify_pct(&self, c: &CombatConstants) -> i32 {
+        (c.fortify_per_turn_pct * self.fortify_turns as i32).min(c.fortify_cap_pct)
+    }
+
+    /// Defensive bonus from fortification, as a fraction (0.25 = +25%)
+    pub fn fortify_bonus(&self, c: &CombatConstants) -> Fixed {
+        Fixed::from_pct(self.fortify_pct(c))
+    }
+
+    /// Strength modifier from being out of supply, as a fraction (zero or negative)
+    pub fn supply_penalty(&self, c: &CombatConstants) -> Fixed {
+        Fixed::from_pct((c.oos_per_turn_pct * self.out_of_supply_turns as i32).max(c.oos_cap_pct))
+    }
+}
+
+pub(crate) fn validate_fortify(
+    state: &State,
+    player: PlayerId,
+    id: UnitId,
+) -> Result<(), SimError> {
+    let unit = state.unit(id).ok_or(ValidationError::UnitNotFound(id))?;
+    validation::check_owner(player, unit.owner)?;
+    if !unit.is_combat() {
+        return Err(ValidationError::CannotFortify(id).into());
+    }
+    if unit.fortified {
+        return Err(ValidationError::AlreadyFortified(id).into());
+    }
+    if unit.moves_left < 1 {
+        return Err(ValidationError::InsufficientAP {
+            needed: 1,
+            available: unit.moves_left,
+        }
+        .into());
+    }
+    Ok(())
+}
+
+/// Fortify an already validated unit; this ends its turn
+pub(crate) fn apply_fortify(state: &mut State, id: UnitId, rec: &mut Recorder) {
+    rec.unit(state, id);
+    let Some(unit) = state.units.get_mut(&id) else {
+        return;
+    };
+    unit.fortified = true;
+    unit.moves_left = 0;
+    rec.emit(Event::UnitFortified { unit: id });
+}
+
+/// Upkeep: fortified units dig in one turn deeper
+pub(crate) fn accrue_fortify(state: &mut State) -> Result<(), SimError> {
+    let max_turns = state.rules()?.combat.max_fortify_turns();
+    for unit in state.units.values_mut().filter(|u| u.fortified) {
+        unit.fortify_turns = unit.fortify_turns.saturating_add(1).min(max_turns);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rules::{Rules, DEFAULT_VERSION};
+    use crate::test_util::new_state;
+
+    fn warrior() -> Unit {
+        Unit::new(
+            UnitId(1),
+            PlayerId(0),
+            "warrior",
+            UnitClass::Combat,
+            TileCoord::new(0, 0),
+            2,
+        )
+    }
+
+    #[test]
+    fn test_new_unit_is_fresh() {
+        let unit = warrior();
+        assert_eq!(unit.hp, MAX_HP);
+        assert_eq!(unit.moves_left, 2);
+        assert!(unit.is_combat());
+        let c = &Rules::load(DEFAULT_VERSION).unwrap().combat;
+        assert_eq!(unit.fortify_bonus(c), Fixed::ZERO);
+        assert_eq!(unit.supply_penalty(c), Fixed::ZERO);
+    }
+
+    #[test]
+    fn test_counter_modifiers_are_capped() {
+        let c = &Rules::load(DEFAULT_VERSION).unwrap().combat;
+        let mut unit = warrior();
+        unit.fortify_turns = 2;
+        assert_eq!(unit.fortify_bonus(c), Fixed::from_pct(20));
+        unit.fortify_turns = 9;
+        assert_eq!(unit.fortify_bonus(c), Fixed::from_pct(25));
+        unit.out_of_supply_turns = 9;
+        assert_eq!(unit.supply_penalty(c), Fixed::from_pct(-30));
+    }
+
+    #[test]
+    fn test_fortify_accrues_and_breaks_on_move() {
+        use crate::{apply_action, end_turn, validate_action, Action};
+
+        let mut state = new_state(8, 8);
+        let id = state
+            .spawn_unit(PlayerId(0), "warrior", TileCoord::new(2, 2))
+            .unwrap();
+        let c = &state.rules().unwrap().combat;
+        let fortify = Action::Fortify { unit: id };
+
+        let effects = apply_action(&mut state, PlayerId(0), fortify.clone()).unwrap();
+        assert_eq!(effects.events, vec![Event::UnitFortified { unit: id }]);
+        let unit = state.unit(id).unwrap();
+        assert_eq!(unit.moves_left, 0);
+        assert_eq!(unit.fortify_bonus(c), Fixed::ZERO);
+        let code = validate_action(&state, PlayerId(0), &fortify)
+            .unwrap_err()
+            .code();
+        assert_eq!(code, Some(30));
+
+        let bonuses: Vec<Fixed> = (0..4)
+            .map(|_| {
+                end_turn(&mut state).unwrap();
+                state.unit(id).unwrap().fortify_bonus(c)
+            })
+            .collect();
+        assert_eq!(bonuses, [10, 20, 25, 25].map(Fixed::from_pct).to_vec());
+
+        let step = Action::MoveUnit {
+            unit: id,
+            path: vec![TileCoord::new(3, 2)],
+            ap: 1,
+        };
+        apply_action(&mut state, PlayerId(0), step).unwrap();
+        let unit = state.unit(id).unwrap();
+        assert!(!unit.fortified);
+        assert_eq!(unit.fortify_bonus(c), Fixed::ZERO);
+    }
+}
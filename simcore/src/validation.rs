@@ -0,0 +1,184 @@
+//! Machine-readable action validation errors
+//!
+//! Every rejection carries a `ValidationError` whose `code()` is stable across releases,
+//! so clients and bots can branch on the number rather than parse the message. Codes
+//! are never renumbered or reused; new variants take the next free number.
+
+use crate::{CityId, PlayerId, TileCoord, UnitId};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// Why an action was rejected
+#[derive(Error, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ValidationError {
+    #[error("unit {0:?} not found")]
+    UnitNotFound(UnitId),
+    #[error("city {0:?} not found")]
+    CityNotFound(CityId),
+    #[error("player {0:?} not found")]
+    PlayerNotFound(PlayerId),
+    #[error("player {player:?} does not own this (owner {owner:?})")]
+    NotOwner { player: PlayerId, owner: PlayerId },
+    #[error("({}, {}) is out of bounds", .0.x, .0.y)]
+    OutOfBounds(TileCoord),
+    #[error("({}, {}) is impassable", .0.x, .0.y)]
+    Impassable(TileCoord),
+    #[error("({}, {}) already holds a unit of that class", .0.x, .0.y)]
+    OneUnitPerTileClash(TileCoord),
+    #[error("needs {needed} AP, {available} left")]
+    InsufficientAP { needed: i32, available: i32 },
+    #[error("needs {needed}, {available} available")]
+    InsufficientFunds { needed: i32, available: i32 },
+    #[error("tech {0} is not available")]
+    TechNotAvailable(String),
+    #[error("policy slot {0} is invalid")]
+    PolicySlotInvalid(i32),
+    #[error("deal {0} not found")]
+    DealNotFound(String),
+    #[error("unknown unit kind {0}")]
+    UnknownUnitKind(String),
+    #[error("unknown district kind {0}")]
+    UnknownDistrictKind(String),
+    #[error("city {0:?} production queue is full")]
+    QueueFull(CityId),
+    #[error("city {0:?} has no free district slots")]
+    NoDistrictSlot(CityId),
+    #[error("({}, {}) cannot hold a district", .0.x, .0.y)]
+    TileUnavailable(TileCoord),
+    #[error("({}, {}) cannot hold a city", .0.x, .0.y)]
+    CityTileUnavailable(TileCoord),
+    #[error("path is empty or not contiguous at ({}, {})", .0.x, .0.y)]
+    PathNotContiguous(TileCoord),
+    #[error("path is blocked by another player at ({}, {})", .0.x, .0.y)]
+    PathBlocked(TileCoord),
+    #[error("declared {declared} AP but the path costs {actual}")]
+    APMismatch { declared: i32, actual: i32 },
+    #[error("zone of control stops movement at ({}, {})", .0.x, .0.y)]
+    ZoneOfControl(TileCoord),
+    #[error("unit {0:?} is not hostile")]
+    NotHostile(UnitId),
+    #[error("target is {distance} tiles away, range is {range}")]
+    OutOfRange { distance: i32, range: i32 },
+    #[error("unit {0:?} cannot attack")]
+    CannotAttack(UnitId),
+    #[error("city {0:?} is not hostile")]
+    CityNotHostile(CityId),
+    #[error("city {0:?} walls are already breached")]
+    WallsBreached(CityId),
+    #[error("city {0:?} cannot strike this turn")]
+    CityCannotStrike(CityId),
+    #[error("unit {0:?} cannot fortify")]
+    CannotFortify(UnitId),
+    #[error("unit {0:?} is already fortified")]
+    AlreadyFortified(UnitId),
+    #[error("tech {0} is frozen")]
+    TechFrozen(String),
+    #[error("unknown policy {0}")]
+    UnknownPolicy(String),
+    #[error("policy {0} is locked")]
+    PolicyLocked(String),
+    #[error("policy {id} does not fit slot {slot}")]
+    PolicyCategoryMismatch { slot: i32, id: String },
+    #[error("policy {0} is already slotted")]
+    PolicyAlreadySlotted(String),
+    #[error("policy slot {slot} is locked until turn {ready_on}")]
+    PolicySlotCooldown { slot: i32, ready_on: i32 },
+    #[error("({}, {}) is outside the city's workable radius", .0.x, .0.y)]
+    DistrictOutOfRange(TileCoord),
+    #[error("district {kind} cannot be placed on ({}, {})", .tile.x, .tile.y)]
+    TerrainUnsuitable { kind: String, tile: TileCoord },
+}
+
+/// Reject the action unless `player` owns the entity it orders
+pub(crate) fn check_owner(player: PlayerId, owner: PlayerId) -> Result<(), ValidationError> {
+    if player != owner {
+        return Err(ValidationError::NotOwner { player, owner });
+    }
+    Ok(())
+}
+
+impl ValidationError {
+    /// Stable numeric code for this rejection reason
+    pub fn code(&self) -> u32 {
+        match self {
+            ValidationError::UnitNotFound(_) => 1,
+            ValidationError::CityNotFound(_) => 2,
+            ValidationError::PlayerNotFound(_) => 3,
+            ValidationError::NotOwner { .. } => 4,
+            ValidationError::OutOfBounds(_) => 5,
+            ValidationError::Impassable(_) => 6,
+            ValidationError::OneUnitPerTileClash(_) => 7,
+            ValidationError::InsufficientAP { .. } => 8,
+            ValidationError::InsufficientFunds { .. } => 9,
+            ValidationError::TechNotAvailable(_) => 10,
+            ValidationError::PolicySlotInvalid(_) => 11,
+            ValidationError::DealNotFound(_) => 12,
+            ValidationError::UnknownUnitKind(_) => 13,
+            ValidationError::UnknownDistrictKind(_) => 14,
+            ValidationError::QueueFull(_) => 15,
+            ValidationError::NoDistrictSlot(_) => 16,
+            ValidationError::TileUnavailable(_) => 17,
+            ValidationError::CityTileUnavailable(_) => 18,
+            ValidationError::PathNotContiguous(_) => 19,
+            ValidationError::PathBlocked(_) => 20,
+            ValidationError::APMismatch { .. } => 21,
+            ValidationError::ZoneOfControl(_) => 22,
+            ValidationError::NotHostile(_) => 23,
+            ValidationError::OutOfRange { .. } => 24,
+            ValidationError::CannotAttack(_) => 25,
+            ValidationError::CityNotHostile(_) => 26,
+            ValidationError::WallsBreached(_) => 27,
+            ValidationError::CityCannotStrike(_) => 28,
+            ValidationError::CannotFortify(_) => 29,
+            ValidationError::AlreadyFortified(_) => 30,
+            ValidationError::TechFrozen(_) => 31,
+            ValidationError::UnknownPolicy(_) => 32,
+            ValidationError::PolicyLocked(_) => 33,
+            ValidationError::PolicyCategoryMismatch { .. } => 34,
+            ValidationError::PolicyAlreadySlotted(_) => 35,
+            ValidationError::PolicySlotCooldown { .. } => 36,
+            ValidationError::DistrictOutOfRange(_) => 37,
+            ValidationError::TerrainUnsuitable { .. } => 38,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_codes_are_stable() {
+        // Pinned: clients branch on these numbers
+        let cases = [
+            (ValidationError::UnitNotFound(UnitId(0)), 1),
+            (
+                ValidationError::NotOwner {
+                    player: PlayerId(1),
+                    owner: PlayerId(0),
+                },
+                4,
+            ),
+            (ValidationError::OutOfBounds(TileCoord::new(-1, 0)), 5),
+            (
+                ValidationError::OneUnitPerTileClash(TileCoord::new(0, 0)),
+                7,
+            ),
+            (
+                ValidationError::InsufficientAP {
+                    needed: 3,
+                    available: 1,
+                },
+                8,
+            ),
+            (
+                ValidationError::CityTileUnavailable(TileCoord::new(0, 0)),
+                18,
+            ),
+            (ValidationError::TechFrozen("writing".into()), 31),
+        ];
+        for (err, code) in cases {
+            assert_eq!(err.code(), code, "{}", err);
+        }
+    }
+}
@@ -0,0 +1,462 @@
+//! Yields engine
+//!
+//! Every city works its center tile plus one tile per citizen. Citizens are placed
+//! city by city in id order, each picking the free tile within the workable radius
+//! that scores best (food first, then production, then everything else), ties going to
+//! the lower coordinate; city centers and district tiles are never worked.
+//!
+//! A city's yield is the sum of, in order: the flat city-center bonus, worked tile
+//! terrain and resources, per-citizen yields, district adjacency and the flat bonuses of
+//! its owner's techs; a district whose tech is frozen pays nothing. Policy percentages then scale that subtotal, one entry per card.
+//! A player's yield is the sum of their cities plus a flat base, which policies also
+//! scale. Any total that would go negative is raised to zero by a `Floor` entry, so the
+//! breakdown always adds up to the total and no yield is ever below zero.
+
+use crate::rules::{Rules, YieldKind, YieldTable};
+use crate::{district, policy, tech, CityId, Fixed, PlayerId, SimError, State, Tile, TileCoord};
+use serde::Serialize;
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// One amount per yield kind
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct Yields {
+    pub food: Fixed,
+    pub production: Fixed,
+    pub gold: Fixed,
+    pub science: Fixed,
+    pub culture: Fixed,
+    pub influence: Fixed,
+}
+
+impl Yields {
+    pub fn get(&self, kind: YieldKind) -> Fixed {
+        match kind {
+            YieldKind::Food => self.food,
+            YieldKind::Production => self.production,
+            YieldKind::Gold => self.gold,
+            YieldKind::Science => self.science,
+            YieldKind::Culture => self.culture,
+            YieldKind::Influence => self.influence,
+        }
+    }
+
+    fn get_mut(&mut self, kind: YieldKind) -> &mut Fixed {
+        match kind {
+            YieldKind::Food => &mut self.food,
+            YieldKind::Production => &mut self.production,
+            YieldKind::Gold => &mut self.gold,
+            YieldKind::Science => &mut self.science,
+            YieldKind::Culture => &mut self.culture,
+            YieldKind::Influence => &mut self.influence,
+        }
+    }
+}
+
+/// Where part of a yield came from
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub enum YieldSource {
+    /// Flat bonus for being a city
+    CityCenter,
+    /// Terrain and resource of a worked tile, including the center
+    Tile(TileCoord),
+    Citizens,
+    District {
+        kind: String,
+        tile: TileCoord,
+    },
+    Tech(String),
+    Policy(String),
+    /// Player yield that does not come from cities
+    Base,
+    /// Raises a negative total to zero
+    Floor,
+}
+
+/// One line of a breakdown
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Contribution {
+    pub source: YieldSource,
+    pub kind: YieldKind,
+    pub amount: Fixed,
+}
+
+/// A total and the contributions that make it up
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct Breakdown {
+    pub total: Yields,
+    pub lines: Vec<Contribution>,
+}
+
+impl Breakdown {
+    fn add(&mut self, source: YieldSource, kind: YieldKind, amount: Fixed) {
+        if amount == Fixed::ZERO {
+            return;
+        }
+        *self.total.get_mut(kind) += amount;
+        self.lines.push(Contribution {
+            source,
+            kind,
+            amount,
+        });
+    }
+
+    fn add_table(&mut self, source: &YieldSource, table: YieldTable) {
+        for kind in YieldKind::ALL {
+            self.add(source.clone(), kind, Fixed::from_int(table.get(kind)));
+        }
+    }
+
+    /// Scale the running total by each active policy card of `owner`
+    fn apply_policies(&mut self, state: &State, rules: &Rules, owner: PlayerId) {
+        let Some(player) = state.player(owner) else {
+            return;
+        };
+        let base = self.total;
+        for (id, def) in policy::active_cards(rules, player) {
+            for kind in YieldKind::ALL {
+                let amount = base.get(kind) * Fixed::from_pct(def.modifiers.pct(kind));
+                self.add(YieldSource::Policy(id.to_string()), kind, amount);
+            }
+        }
+    }
+
+    fn floor(&mut self) {
+        for kind in YieldKind::ALL {
+            let total = self.total.get(kind);
+            if total < Fixed::ZERO {
+                self.add(YieldSource::Floor, kind, -total);
+            }
+        }
+    }
+}
+
+/// What a city produces this turn
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CityYields {
+    pub city: CityId,
+    pub owner: PlayerId,
+    /// Tiles worked by citizens, not counting the center
+    pub worked: Vec<TileCoord>,
+    pub yields: Breakdown,
+}
+
+/// What a player produces this turn
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PlayerYields {
+    pub player: PlayerId,
+    /// Sum of `cities` plus `own`
+    pub total: Yields,
+    pub cities: Vec<CityId>,
+    /// Player-level contributions, with their own floor
+    pub own: Breakdown,
+}
+
+/// Yields for every city and every player with a city or a roster entry
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct YieldReport {
+    pub cities: BTreeMap<CityId, CityYields>,
+    pub players: BTreeMap<PlayerId, PlayerYields>,
+}
+
+/// Terrain plus resource yields of a tile
+pub fn tile_yields(rules: &Rules, tile: &Tile) -> YieldTable {
+    let resource = tile
+        .resource
+        .and_then(|r| rules.resources.get(&r).copied())
+        .unwrap_or_default();
+    rules.terrain(tile.terrain).yields() + resource
+}
+
+/// Citizen preference: food, then production, then the rest
+fn score(y: YieldTable) -> i32 {
+    4 * y.food + 2 * y.production + y.gold + y.science + y.culture + y.influence
+}
+
+/// Tiles each city's citizens work, in city id order
+pub fn worked_tiles(state: &State, rules: &Rules) -> BTreeMap<CityId, Vec<TileCoord>> {
+    let mut taken: BTreeSet<TileCoord> = state
+        .cities
+        .values()
+        .flat_map(|c| std::iter::once(c.pos).chain(c.districts.iter().map(|d| d.tile)))
+        .collect();
+    let mut out = BTreeMap::new();
+    for city in state.cities.values() {
+        let mut candidates: Vec<(i32, TileCoord)> = state
+            .map
+            .tiles_within(city.pos, rules.city.workable_radius)
+            .into_iter()
+            .filter(|c| !taken.contains(c))
+            .filter_map(|c| Some((score(tile_yields(rules, state.map.tile(c)?)), c)))
+            .collect();
+        candidates.sort_by_key(|&(s, c)| (Reverse(s), c));
+        let worked: Vec<TileCoord> = candidates
+            .into_iter()
+            .take(city.population.max(0) as usize)
+            .map(|(_, c)| c)
+            .collect();
+        taken.extend(worked.iter().copied());
+        out.insert(city.id, worked);
+    }
+    out
+}
+
+/// Compute this turn's yields without changing anything
+pub fn compute(state: &State) -> Result<YieldReport, SimError> {
+    let rules = state.rules()?;
+    let worked = worked_tiles(state, rules);
+    let mut cities = BTreeMap::new();
+    for city in state.cities.values() {
+        let mut b = Breakdown::default();
+        b.add_table(&YieldSource::CityCenter, rules.yields.city_center);
+        let tiles = std::iter::once(city.pos).chain(worked[&city.id].iter().copied());
+        for coord in tiles {
+            if let Some(tile) = state.map.tile(coord) {
+                b.add_table(&YieldSource::Tile(coord), tile_yields(rules, tile));
+            }
+        }
+        let per_citizen = rules.yields.per_citizen_pct;
+        for kind in YieldKind::ALL {
+            let amount = Fixed::from_pct(per_citizen.get(kind)).mul_int(city.population.max(0));
+            b.add(YieldSource::Citizens, kind, amount);
+        }
+        for d in &city.districts {
+            let Some(def) = rules.district(&d.kind) else {
+                continue;
+            };
+            if district::is_active(state, city.owner, def) {
+                let source = YieldSource::District {
+                    kind: d.kind.clone(),
+                    tile: d.tile,
+                };
+                let amount = Fixed::from_int(district::adjacency(state, def, d.tile));
+                b.add(source, def.output, amount);
+            }
+        }
+        if let Some(player) = state.player(city.owner) {
+            for (id, def) in &rules.techs {
+                if tech::has(player, id) {
+                    b.add_table(&YieldSource::Tech(id.clone()), def.city_yields);
+                }
+            }
+        }
+        b.apply_policies(state, rules, city.owner);
+        b.floor();
+        cities.insert(
+            city.id,
+            CityYields {
+                city: city.id,
+                owner: city.owner,
+                worked: worked[&city.id].clone(),
+                yields: b,
+            },
+        );
+    }
+
+    let owners: BTreeSet<PlayerId> = state
+        .players
+        .keys()
+        .copied()
+        .chain(state.cities.values().map(|c| c.owner))
+        .collect();
+    let mut players = BTreeMap::new();
+    for owner in owners {
+        let mut own = Breakdown::default();
+        if state.player(owner).is_some() {
+            own.add_table(&YieldSource::Base, rules.yields.player_base);
+            own.apply_policies(state, rules, owner);
+            own.floor();
+        }
+        let mut total = own.total;
+        let mut ids = Vec::new();
+        for c in cities.values().filter(|c: &&CityYields| c.owner == owner) {
+            for kind in YieldKind::ALL {
+                *total.get_mut(kind) += c.yields.total.get(kind);
+            }
+            ids.push(c.city);
+        }
+        players.insert(
+            owner,
+            PlayerYields {
+                player: owner,
+                total,
+                cities: ids,
+                own,
+            },
+        );
+    }
+    Ok(YieldReport { cities, players })
+}
+
+/// `player`'s yields this turn; all zero if they have no cities and no roster entry
+pub fn player_yields(state: &State, player: PlayerId) -> Result<Yields, SimError> {
+    Ok(compute(state)?
+        .players
+        .get(&player)
+        .map(|p| p.total)
+        .unwrap_or_default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::new_state;
+    use crate::{District, Resource, Terrain};
+
+    fn setup() -> (State, CityId) {
+        let mut state = new_state(12, 12);
+        let city = state
+            .found_city(PlayerId(0), "Jericho", TileCoord::new(5, 5))
+            .unwrap();
+        (state, city)
+    }
+
+    fn sum(b: &Breakdown, kind: YieldKind) -> Fixed {
+        b.lines
+            .iter()
+            .filter(|l| l.kind == kind)
+            .fold(Fixed::ZERO, |acc, l| acc + l.amount)
+    }
+
+    #[test]
+    fn test_citizens_work_best_free_tiles() {
+        let (mut state, city) = setup();
+        let hills = TileCoord::new(3, 3);
+        state.map.set_terrain(hills, Terrain::Hills).unwrap();
+        state.map.tile_mut(TileCoord::new(7, 7)).unwrap().resource = Some(Resource::Wheat);
+        state.cities.get_mut(&city).unwrap().population = 2;
+        let second = state
+            .found_city(PlayerId(0), "Jarmo", TileCoord::new(8, 7))
+            .unwrap();
+
+        let rules = state.rules().unwrap();
+        let worked = worked_tiles(&state, rules);
+        // Wheat beats plain grassland; the next grassland goes to the lowest coordinate
+        assert_eq!(worked[&city][0], TileCoord::new(7, 7));
+        assert!(!worked[&second].contains(&TileCoord::new(7, 7)));
+        assert!(!worked[&city].contains(&hills));
+        assert_eq!(worked[&city].len(), 2);
+    }
+
+    #[test]
+    fn test_breakdown_sums_to_total() {
+        let (mut state, city) = setup();
+        let player = state.players.get_mut(&PlayerId(0)).unwrap();
+        player
+            .known_techs
+            .extend(["foraging", "pottery", "astrology"].map(String::from));
+        player.policies.slotted.insert(1, "god_king".into());
+        state
+            .cities
+            .get_mut(&city)
+            .unwrap()
+            .districts
+            .push(District {
+                kind: "holy_site".into(),
+                tile: TileCoord::new(6, 5),
+            });
+        state
+            .map
+            .set_terrain(TileCoord::new(7, 5), Terrain::Mountain)
+            .unwrap();
+
+        let report = compute(&state).unwrap();
+        let b = &report.cities[&city].yields;
+        for kind in YieldKind::ALL {
+            assert_eq!(sum(b, kind), b.total.get(kind), "{:?}", kind);
+            assert!(b.total.get(kind) >= Fixed::ZERO);
+        }
+        let has = |source: YieldSource| b.lines.iter().any(|l| l.source == source);
+        assert!(has(YieldSource::Tech("pottery".into())));
+        assert!(has(YieldSource::Policy("god_king".into())));
+        assert!(has(YieldSource::District {
+            kind: "holy_site".into(),
+            tile: TileCoord::new(6, 5),
+        }));
+        // Center 1 + grassland center 2 + one worked grassland 2 + pottery 1
+        assert_eq!(b.total.food, Fixed::from_int(6));
+        // (center 2 + holy site 1) * 1.2
+        let gold = Fixed::from_int(3);
+        assert_eq!(b.total.gold, gold + gold * Fixed::from_pct(20));
+
+        let totals = &report.players[&PlayerId(0)];
+        assert_eq!(totals.total.influence, Fixed::ONE);
+        assert_eq!(totals.total.gold, b.total.gold);
+        assert_eq!(player_yields(&state, PlayerId(0)).unwrap(), totals.total);
+    }
+
+    #[test]
+    fn test_negative_policies_floor_at_zero() {
+        let (mut state, city) = setup();
+        let mut rules = state.rules().unwrap().clone();
+        rules
+            .policies
+            .get_mut("god_king")
+            .unwrap()
+            .modifiers
+            .gold_pct = -60;
+        rules
+            .policies
+            .get_mut("discipline")
+            .unwrap()
+            .modifiers
+            .gold_pct = -60;
+        let player = state.players.get_mut(&PlayerId(0)).unwrap();
+        player.policies.slotted.insert(0, "discipline".into());
+        player.policies.slotted.insert(1, "god_king".into());
+
+        let mut b = Breakdown::default();
+        b.add(YieldSource::CityCenter, YieldKind::Gold, Fixed::from_int(2));
+        b.apply_policies(&state, &rules, PlayerId(0));
+        assert!(b.total.gold < Fixed::ZERO);
+        b.floor();
+        assert_eq!(b.total.gold, Fixed::ZERO);
+        assert_eq!(b.lines.last().map(|l| &l.source), Some(&YieldSource::Floor));
+        assert_eq!(sum(&b, YieldKind::Gold), Fixed::ZERO);
+        assert!(compute(&state).unwrap().cities[&city].yields.total.gold >= Fixed::ZERO);
+    }
+
+    #[test]
+    fn test_frozen_tech_stops_district_output() {
+        let (mut state, city) = setup();
+        let player = state.players.get_mut(&PlayerId(0)).unwrap();
+        player.known_techs.insert("writing".into());
+        let campus = TileCoord::new(6, 5);
+        state
+            .cities
+            .get_mut(&city)
+            .unwrap()
+            .districts
+            .push(District {
+                kind: "campus".into(),
+                tile: campus,
+            });
+        state
+            .map
+            .set_terrain(TileCoord::new(7, 5), Terrain::Mountain)
+            .unwrap();
+        let source = YieldSource::District {
+            kind: "campus".into(),
+            tile: campus,
+        };
+        let line = |state: &State| {
+            let report = compute(state).unwrap();
+            let lines = &report.cities[&city].yields.lines;
+            lines.iter().find(|l| l.source == source).map(|l| l.amount)
+        };
+        assert_eq!(line(&state), Some(Fixed::ONE));
+
+        crate::freeze_tech(
+            &mut state,
+            PlayerId(0),
+            "writing",
+            tech::FreezeCause::Sanctions,
+            None,
+        )
+        .unwrap();
+        assert_eq!(line(&state), None);
+        let rules = state.rules().unwrap();
+        let output = district::city_output(&state, rules, state.city(city).unwrap());
+        assert_eq!(output.get(&YieldKind::Science), None);
+    }
+}
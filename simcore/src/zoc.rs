@@ -0,0 +1,94 @@
+//! Zone of control
+//!
+//! Every living combat unit controls the tiles adjacent to it against other players.
+//! A unit stepping from one enemy-controlled tile into another must stop there. Unit
+//! kinds flagged `ignores_zoc` in the rules move freely.
+
+use crate::rules::Rules;
+use crate::{PlayerId, SimError, State, TileCoord, Unit};
+
+/// `unit` projects a zone of control against `player`
+pub fn exerts_against(unit: &Unit, player: PlayerId) -> bool {
+    unit.owner != player && unit.is_combat() && unit.is_alive()
+}
+
+/// Some other player's combat unit is adjacent to `coord`
+pub fn is_controlled(state: &State, player: PlayerId, coord: TileCoord) -> bool {
+    state
+        .map
+        .neighbors(coord)
+        .any(|n| state.units_at(n).any(|u| exerts_against(u, player)))
+}
+
+/// The rules let `unit` move through zones of control freely
+pub fn ignores(rules: &Rules, unit: &Unit) -> bool {
+    rules.unit(&unit.kind).is_some_and(|def| def.ignores_zoc)
+}
+
+/// Whether ZOC applies to `unit` at all
+pub fn is_subject(state: &State, unit: &Unit) -> Result<bool, SimError> {
+    Ok(!ignores(state.rules()?, unit))
+}
+
+/// Stepping `unit` from `from` into the adjacent `to` ends its movement
+pub fn stops_movement(
+    state: &State,
+    unit: &Unit,
+    from: TileCoord,
+    to: TileCoord,
+) -> Result<bool, SimError> {
+    Ok(is_subject(state, unit)?
+        && is_controlled(state, unit.owner, from)
+        && is_controlled(state, unit.owner, to))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{mv, new_state};
+    use crate::{validate_action, UnitId};
+
+    fn setup(kind: &str) -> (State, UnitId) {
+        let mut state = new_state(10, 10);
+        // Enemy warrior at (4, 2) controls (3, 2), (5, 2), (3, 1), (4, 1), (3, 3), (4, 3)
+        state
+            .spawn_unit(PlayerId(1), "warrior", TileCoord::new(4, 2))
+            .unwrap();
+        let unit = state
+            .spawn_unit(PlayerId(0), kind, TileCoord::new(3, 2))
+            .unwrap();
+        (state, unit)
+    }
+
+    #[test]
+    fn test_control_comes_from_enemy_combat_units() {
+        let (state, _) = setup("warrior");
+        assert!(is_controlled(&state, PlayerId(0), TileCoord::new(3, 2)));
+        assert!(is_controlled(&state, PlayerId(0), TileCoord::new(4, 3)));
+        assert!(!is_controlled(&state, PlayerId(0), TileCoord::new(2, 2)));
+        assert!(!is_controlled(&state, PlayerId(1), TileCoord::new(3, 2)));
+    }
+
+    #[test]
+    fn test_moving_between_controlled_tiles_stops() {
+        let (mut state, unit) = setup("horseman");
+        // (3, 2) -> (3, 3) -> (3, 4): the first step stays inside the zone
+        let blocked = mv(unit, &[(3, 3), (3, 4)], 2);
+        let err = validate_action(&state, PlayerId(0), &blocked).unwrap_err();
+        assert_eq!(err.code(), Some(22));
+
+        // Ending the path on the controlled tile is fine, and uses up all movement
+        crate::apply_action(&mut state, PlayerId(0), mv(unit, &[(3, 3)], 1)).unwrap();
+        assert_eq!(state.unit(unit).unwrap().moves_left, 0);
+
+        // Leaving the zone is never restricted
+        let (state, unit) = setup("horseman");
+        assert!(validate_action(&state, PlayerId(0), &mv(unit, &[(2, 2), (2, 3)], 2)).is_ok());
+    }
+
+    #[test]
+    fn test_rules_knob_ignores_zoc() {
+        let (state, unit) = setup("builder");
+        assert!(validate_action(&state, PlayerId(0), &mv(unit, &[(3, 3), (3, 4)], 2)).is_ok());
+    }
+}
@@ -0,0 +1,80 @@
+//! Golden state_hash vectors
+//!
+//! These values pin the canonical encoding. If a change to `State` (a new field, a
+//! reordered enum, a different default) moves one of them, the change alters replay
+//! compatibility: update the constant in the same commit and say so in its message.
+
+use simcore::{
+    apply_action, state_hash, Action, HexDir, Map, PlayerId, Resource, State, Terrain, TileCoord,
+};
+
+fn scenario() -> State {
+    let mut state = State::with_seed(0x5EED);
+    let mut map = Map::new(8, 6).unwrap();
+    map.set_terrain(TileCoord::new(3, 2), Terrain::Forest)
+        .unwrap();
+    map.set_terrain(TileCoord::new(4, 2), Terrain::Hills)
+        .unwrap();
+    map.set_terrain(TileCoord::new(6, 4), Terrain::Mountain)
+        .unwrap();
+    map.set_river(TileCoord::new(2, 2), HexDir::East).unwrap();
+    map.tile_mut(TileCoord::new(1, 1)).unwrap().resource = Some(Resource::Wheat);
+    state.map = map;
+    state.add_player(PlayerId(0), "Natufians");
+    state.add_player(PlayerId(1), "Khiamians");
+
+    let city = state
+        .found_city(PlayerId(0), "Jericho", TileCoord::new(2, 2))
+        .unwrap();
+    state
+        .spawn_unit(PlayerId(0), "warrior", TileCoord::new(2, 3))
+        .unwrap();
+    state
+        .spawn_unit(PlayerId(1), "archer", TileCoord::new(5, 3))
+        .unwrap();
+    apply_action(
+        &mut state,
+        PlayerId(0),
+        Action::BuildUnit {
+            city,
+            kind: "settler".to_string(),
+        },
+    )
+    .unwrap();
+    state
+}
+
+#[test]
+fn golden_empty_state() {
+    assert_eq!(state_hash(&State::new()).to_string(), EMPTY);
+}
+
+#[test]
+fn golden_seeded_state() {
+    assert_eq!(state_hash(&State::with_seed(42)).to_string(), SEEDED);
+}
+
+#[test]
+fn golden_scenario_state() {
+    assert_eq!(state_hash(&scenario()).to_string(), SCENARIO);
+}
+
+#[test]
+fn golden_scenario_after_end_turn() {
+    let mut state = scenario();
+    apply_action(&mut state, PlayerId(0), Action::EndTurn).unwrap();
+    assert_eq!(state_hash(&state).to_string(), SCENARIO_TURN_1);
+}
+
+#[test]
+fn hash_survives_serde_round_trip() {
+    let state = scenario();
+    let json = serde_json::to_string(&state).unwrap();
+    let restored: State = serde_json::from_str(&json).unwrap();
+    assert_eq!(state_hash(&state), state_hash(&restored));
+}
+
+const EMPTY: &str = "301d3b535bcb68430afec36cdaa7684b";
+const SEEDED: &str = "af2f0884d6efa6143a08905e714831a2";
+const SCENARIO: &str = "29c5d6a618903ff8281ad50b38c184c3";
+const SCENARIO_TURN_1: &str = "c9a2a9f9c8f1ff1e2ca02c39ebae2c63";